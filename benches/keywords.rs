@@ -0,0 +1,93 @@
+//! Compares keyword lookup through a runtime-built `HashMap`, the way the
+//! scanner used to do it, against the `Dialect` switch, and times scanning a
+//! large identifier-heavy source.
+//!
+//! Run with `cargo bench --bench keywords`.
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use interpreter_starter_rust::{Dialect, Scanner, TokenType};
+
+const KEYWORDS: [(&str, TokenType); 16] = [
+    ("and", TokenType::And),
+    ("class", TokenType::Class),
+    ("else", TokenType::Else),
+    ("false", TokenType::False),
+    ("for", TokenType::For),
+    ("fun", TokenType::Fun),
+    ("if", TokenType::If),
+    ("nil", TokenType::Nil),
+    ("or", TokenType::Or),
+    ("print", TokenType::Print),
+    ("return", TokenType::Return),
+    ("super", TokenType::Super),
+    ("this", TokenType::This),
+    ("true", TokenType::True),
+    ("var", TokenType::Var),
+    ("while", TokenType::While),
+];
+
+const IDENTIFIERS: [&str; 12] = [
+    "count", "index", "fib", "total", "result", "thing", "value", "falsey", "format", "node",
+    "iffy", "classify",
+];
+
+/// A deterministic mix of keywords and identifiers, most of which share a
+/// length and first letter with a keyword.
+fn synthetic_words(count: usize) -> Vec<&'static str> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            match state % 3 {
+                0 => KEYWORDS[(state >> 8) as usize % KEYWORDS.len()].0,
+                _ => IDENTIFIERS[(state >> 8) as usize % IDENTIFIERS.len()],
+            }
+        })
+        .collect()
+}
+
+fn time<F: FnMut() -> usize>(name: &str, units: usize, unit: &str, mut f: F) {
+    // warm up, then take the best of a few runs
+    black_box(f());
+    let best = (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or(Duration::ZERO);
+
+    let per_sec = units as f64 / best.as_secs_f64();
+    println!("{name:<28} {best:>12.2?}  {per_sec:>14.0} {unit}/s");
+}
+
+fn main() {
+    let words = synthetic_words(2_000_000);
+
+    let map: HashMap<&str, TokenType> = KEYWORDS.into_iter().collect();
+    time("HashMap lookup", words.len(), "words", || {
+        words
+            .iter()
+            .filter_map(|w| map.get(black_box(*w)).copied())
+            .count()
+    });
+
+    let dialect = Dialect::lox();
+    time("Dialect::keyword (switch)", words.len(), "words", || {
+        words
+            .iter()
+            .filter_map(|w| dialect.keyword(black_box(w)))
+            .count()
+    });
+
+    let source = words.join(" ");
+    time("scan identifiers", words.len(), "tokens", || {
+        Scanner::from(source.as_str()).count()
+    });
+}
@@ -0,0 +1,441 @@
+//! Measures scanning throughput in tokens and megabytes per second on a few
+//! generated corpora: typical ASCII Lox code, code heavy on comments and
+//! whitespace, string and number literals, and code with non-ASCII
+//! identifiers and strings.
+//!
+//! Each corpus is scanned by `Scanner` and by a copy of the scanning loop
+//! it replaced, which decodes every character through a `Peekable<Chars>`.
+//! Tokens in typical code are only a few bytes long, so most of the time
+//! goes into building tokens, which both do alike. Reading bytes instead of
+//! characters pays off on long runs: comments, whitespace and literals.
+//! Non-ASCII identifiers are decoded and checked by both.
+//!
+//! Run with `cargo bench --bench scanner`.
+
+use std::borrow::Cow;
+use std::hint::black_box;
+use std::iter::Peekable;
+use std::num::NonZeroUsize;
+use std::str::Chars;
+use std::time::{Duration, Instant};
+
+use interpreter_starter_rust::{
+    Dialect, Literal, Position, ScanError, Scanner, SourceId, Span, Token, TokenType,
+};
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/syntax/unicode/mod.rs"]
+mod unicode;
+
+const PROGRAM: &str = r#"
+class Tree {
+  init(left, value, right) {
+    this.left = left;
+    this.value = value;
+    this.right = right;
+  }
+
+  sum() {
+    var total = this.value;
+    if (this.left != nil) total = total + this.left.sum();
+    if (this.right != nil) total = total + this.right.sum();
+    return total;
+  }
+}
+
+fun fib(n) {
+  if (n <= 1) return n;
+  return fib(n - 2) + fib(n - 1);
+}
+
+for (var i = 0; i < 20; i = i + 1) {
+  print fib(i) * 2 >= 10 and !false or i == 3;
+}
+"#;
+
+const COMMENTED: &str = r#"
+// Computes the greatest common divisor of two numbers using the
+// Euclidean algorithm. Both arguments must be non-negative.
+fun gcd(a, b) {
+    /* The loop invariant is that gcd(a, b) never changes:
+       gcd(a, b) == gcd(b, a % b). */
+    while (b != 0) {
+        var t = b;      // remember b
+        b = a % b;      // shrink the pair
+        a = t;
+    }
+    return a;           // b is zero, a is the answer
+}
+"#;
+
+const LITERALS: &str = r#"
+var greeting = "Hello, world! This is a fairly long string literal.";
+var escaped = "tab\there, newline\nthere, quote \" and backslash \\";
+var numbers = 3.14159 + 2.71828 * 1_000_000 - 0x7fff_ffff / 6.02e23;
+var template = "total: ${count * 2} items at ${price} each";
+"#;
+
+const UNICODE: &str = r#"
+var größe = 180;
+var café = "crème brûlée ☕ für alle";
+fun παράδειγμα(χ) { return χ * größe; }
+print παράδειγμα(2) + "日本語のテキスト";
+"#;
+
+/// The scanning loop before the byte fast path, reduced to the tokens that
+/// appear in the corpora. It checks them the way `Scanner` does, but keeps
+/// no trivia and panics on invalid input.
+struct CharScanner<'a> {
+    source: &'a str,
+    c_iter: Peekable<Chars<'a>>,
+    start: usize,
+    current: usize,
+    start_pos: Position,
+    line: NonZeroUsize,
+    column: NonZeroUsize,
+    emitted_eof: bool,
+    interpolations: Vec<usize>, // unmatched `{` inside each interpolation
+    dialect: &'a Dialect,
+}
+
+impl<'a> CharScanner<'a> {
+    fn new(source: &'a str, dialect: &'a Dialect) -> Self {
+        Self {
+            source,
+            c_iter: source.chars().peekable(),
+            start: 0,
+            current: 0,
+            start_pos: Position::default(),
+            line: NonZeroUsize::MIN,
+            column: NonZeroUsize::MIN,
+            emitted_eof: false,
+            interpolations: vec![],
+            dialect,
+        }
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.c_iter.next()?;
+        self.current += c.len_utf8();
+
+        if c == '\n' {
+            self.line = self.line.saturating_add(1);
+            self.column = NonZeroUsize::MIN;
+        } else {
+            self.column = self.column.saturating_add(1);
+        }
+
+        Some(c)
+    }
+
+    fn advance_if<F: Fn(char) -> bool>(&mut self, predicate: F) -> Option<char> {
+        let c = self.peek()?;
+
+        if !predicate(*c) {
+            return None;
+        }
+
+        self.advance()
+    }
+
+    fn advance_while<F: Fn(char) -> bool>(&mut self, predicate: F) {
+        while let Some(c) = self.peek() {
+            if !predicate(*c) {
+                break;
+            }
+
+            self.advance();
+        }
+    }
+
+    fn peek(&mut self) -> Option<&char> {
+        self.c_iter.peek()
+    }
+
+    fn peek_next(&mut self) -> Option<char> {
+        let mut iter = self.c_iter.clone();
+        iter.next();
+        iter.next()
+    }
+
+    fn token(&self, token_type: TokenType, literal: Literal<'a>) -> Option<Token<'a>> {
+        assert!(!self.dialect.is_disabled(token_type));
+        let end_pos = Position::new(self.line, self.column);
+        let span = Span::new(self.start, self.current, self.start_pos, end_pos)
+            .with_source(SourceId::default());
+        let lexeme = &self.source[self.start..self.current];
+        Some(Token::new(token_type, lexeme, literal, span))
+    }
+
+    fn simple(&self, token_type: TokenType) -> Option<Token<'a>> {
+        self.token(token_type, Literal::None)
+    }
+
+    fn scan_string(&mut self, continued: bool) -> Option<Token<'a>> {
+        let mut value: Option<String> = None;
+        let mut interpolated = false;
+
+        while let Some(&c) = self.peek() {
+            match c {
+                '"' => break,
+                '$' if self.peek_next() == Some('{') => {
+                    interpolated = true;
+                    break;
+                }
+                '\\' => {
+                    let escape_start = self.current;
+                    self.advance();
+                    let c = match self.advance() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some(c @ ('"' | '\\' | '$')) => c,
+                        c => panic!("unsupported escape {c:?}"),
+                    };
+                    value
+                        .get_or_insert_with(|| {
+                            self.source[self.start + 1..escape_start].to_string()
+                        })
+                        .push(c);
+                }
+                c => {
+                    self.advance();
+                    if let Some(value) = value.as_mut() {
+                        value.push(c);
+                    }
+                }
+            }
+        }
+
+        let end = self.current;
+        self.advance().expect("unterminated string");
+        if interpolated {
+            self.advance();
+            self.interpolations.push(0);
+        }
+
+        let literal = match value {
+            Some(value) => Cow::Owned(value),
+            None => Cow::Borrowed(&self.source[self.start + 1..end]),
+        };
+        let token_type = match (continued, interpolated) {
+            (false, false) => TokenType::String,
+            (false, true) => TokenType::StringStart,
+            (true, true) => TokenType::StringMiddle,
+            (true, false) => TokenType::StringEnd,
+        };
+        self.token(token_type, Literal::String(literal))
+    }
+
+    fn scan_number(&mut self, first: char) -> Option<Token<'a>> {
+        if first == '0' && self.advance_if(|c| c == 'x').is_some() {
+            self.advance_while(|c| c.is_ascii_alphanumeric() || c == '_');
+            let digits = self.source[self.start + 2..self.current].replace('_', "");
+            let num = i64::from_str_radix(&digits, 16).expect("valid hexadecimal");
+            return self.token(TokenType::Number, Literal::Integer(num));
+        }
+
+        self.scan_digits(self.start);
+        let mut is_float = false;
+        if self.peek() == Some(&'.') && self.peek_next().is_some_and(|c| c.is_ascii_digit()) {
+            self.advance();
+            self.scan_digits(self.current);
+            is_float = true;
+        }
+        if self.advance_if(|c| c == 'e' || c == 'E').is_some() {
+            self.advance_if(|c| c == '+' || c == '-');
+            self.scan_digits(self.current);
+            is_float = true;
+        }
+
+        let digits = self.source[self.start..self.current].replace('_', "");
+        let literal = match is_float {
+            true => Literal::Float(digits.parse().expect("valid float")),
+            false => Literal::Integer(digits.parse().expect("valid integer")),
+        };
+        self.token(TokenType::Number, literal)
+    }
+
+    fn scan_digits(&mut self, start: usize) {
+        self.advance_while(|c| c.is_ascii_digit() || c == '_');
+
+        let digits = &self.source[start..self.current];
+        assert!(digits.bytes().any(|b| b.is_ascii_digit()) && !digits.ends_with('_'));
+    }
+
+    fn scan_identifier(&mut self) -> Option<Token<'a>> {
+        self.advance_while(unicode::is_xid_continue);
+        let lexeme = &self.source[self.start..self.current];
+
+        if !lexeme.is_ascii()
+            && !unicode::is_single_script(lexeme)
+            && unicode::is_confusable(lexeme)
+        {
+            panic!("confusable identifier {lexeme}");
+        }
+
+        self.simple(
+            self.dialect
+                .keyword(lexeme)
+                .unwrap_or(TokenType::Identifier),
+        )
+    }
+
+    fn scan_token(&mut self) -> Option<Token<'a>> {
+        let c = self.advance()?;
+
+        match c {
+            '(' => self.simple(TokenType::LeftParen),
+            ')' => self.simple(TokenType::RightParen),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.simple(TokenType::LeftBrace)
+            }
+            '}' => match self.interpolations.last_mut() {
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.scan_string(true)
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    self.simple(TokenType::RightBrace)
+                }
+                None => self.simple(TokenType::RightBrace),
+            },
+            '.' => self.simple(TokenType::Dot),
+            ';' => self.simple(TokenType::Semicolon),
+            ',' => self.simple(TokenType::Comma),
+            '%' => self.simple(TokenType::Percent),
+            '*' => match self.advance_if(|c| c == '*' || c == '=') {
+                Some(c) => panic!("unsupported operator *{c}"),
+                _ => self.simple(TokenType::Star),
+            },
+            '+' => match self.advance_if(|c| c == '+' || c == '=') {
+                Some(c) => panic!("unsupported operator +{c}"),
+                _ => self.simple(TokenType::Plus),
+            },
+            '-' => match self.advance_if(|c| c == '-' || c == '=' || c == '>') {
+                Some(c) => panic!("unsupported operator -{c}"),
+                _ => self.simple(TokenType::Minus),
+            },
+            '!' => match self.advance_if(|c| c == '=') {
+                Some(_) => self.simple(TokenType::BangEqual),
+                _ => self.simple(TokenType::Bang),
+            },
+            '=' => match self.advance_if(|c| c == '=' || c == '>') {
+                Some('>') => panic!("unsupported operator =>"),
+                Some(_) => self.simple(TokenType::EqualEqual),
+                _ => self.simple(TokenType::Equal),
+            },
+            '>' => match self.advance_if(|c| c == '=') {
+                Some(_) => self.simple(TokenType::GreaterEqual),
+                _ => self.simple(TokenType::Greater),
+            },
+            '<' => match self.advance_if(|c| c == '=') {
+                Some(_) => self.simple(TokenType::LessEqual),
+                _ => self.simple(TokenType::Less),
+            },
+            '/' => match self.advance_if(|c| c == '/' || c == '*' || c == '=') {
+                Some('/') => {
+                    self.advance_while(|c| c != '\n');
+                    None
+                }
+                Some('*') => {
+                    let mut depth = 1;
+                    while depth > 0 {
+                        match self.advance() {
+                            Some('/') if self.advance_if(|c| c == '*').is_some() => depth += 1,
+                            Some('*') if self.advance_if(|c| c == '/').is_some() => depth -= 1,
+                            Some(_) => {}
+                            None => panic!("unterminated block comment"),
+                        }
+                    }
+                    None
+                }
+                Some(_) => panic!("unsupported operator /="),
+                _ => self.simple(TokenType::Slash),
+            },
+            ' ' | '\r' | '\t' => {
+                self.advance_while(|c| matches!(c, ' ' | '\r' | '\t'));
+                None
+            }
+            '\n' => None,
+            '"' => self.scan_string(false),
+            c if c.is_ascii_digit() => self.scan_number(c),
+            c if unicode::is_xid_start(c) || c == '_' => self.scan_identifier(),
+            c => panic!("unexpected character {c:?}"),
+        }
+    }
+}
+
+impl<'a> Iterator for CharScanner<'a> {
+    type Item = Result<Token<'a>, ScanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.start = self.current;
+            self.start_pos = Position::new(self.line, self.column);
+
+            if self.peek().is_none() {
+                if self.emitted_eof {
+                    return None;
+                }
+                self.emitted_eof = true;
+                return self.simple(TokenType::Eof).map(Ok);
+            }
+
+            if let Some(token) = self.scan_token() {
+                return Some(Ok(token));
+            }
+        }
+    }
+}
+
+/// Prints the best of a few runs of `scan`, which returns the number of
+/// tokens it produced.
+fn time(name: &str, bytes: usize, mut scan: impl FnMut() -> usize) {
+    // warm up, then take the best of a few runs
+    let tokens = black_box(scan());
+    let best = (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(scan());
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or(Duration::ZERO);
+
+    let seconds = best.as_secs_f64();
+    println!(
+        "{name:<20} {best:>10.2?}  {:>8.2} Mtokens/s  {:>8.1} MB/s",
+        tokens as f64 / seconds / 1e6,
+        bytes as f64 / seconds / 1e6,
+    );
+}
+
+fn main() {
+    for (name, snippet) in [
+        ("program", PROGRAM),
+        ("comments", COMMENTED),
+        ("literals", LITERALS),
+        ("unicode", UNICODE),
+    ] {
+        // both scanners have to do the same work to be comparable
+        let sample = snippet.repeat(3);
+        let dialect = Dialect::lox();
+        assert_eq!(
+            CharScanner::new(&sample, &dialect).collect::<Vec<_>>(),
+            Scanner::from(sample.as_str()).collect::<Vec<_>>()
+        );
+
+        // roughly 8 MB of each
+        let source = snippet.repeat(8_000_000 / snippet.len());
+        time(&format!("{name} (chars)"), source.len(), || {
+            CharScanner::new(black_box(&source), &dialect).count()
+        });
+        time(&format!("{name} (bytes)"), source.len(), || {
+            Scanner::from(black_box(source.as_str())).count()
+        });
+    }
+}
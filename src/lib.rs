@@ -1,3 +1,13 @@
 mod syntax;
 
+pub use syntax::chunked::{ByteChunks, ChunkedScanner, ReadChunks};
+pub use syntax::diagnostic::{Diagnostic, Label, Renderer};
+pub use syntax::dialect::Dialect;
+pub use syntax::error::ScanError;
+pub use syntax::explain::explain;
+pub use syntax::incremental::{Edit, Relexed};
+pub use syntax::json;
 pub use syntax::scanner::Scanner;
+pub use syntax::source::{SourceFile, SourceId, SourceMap};
+pub use syntax::span::{Position, Span};
+pub use syntax::token::{Literal, Precedence, Token, TokenType, Trivia, TriviaKind};
@@ -1,47 +1,277 @@
 use std::env;
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Read};
 use std::process::exit;
 
-use interpreter_starter_rust::Scanner;
+use interpreter_starter_rust::{explain, json, Diagnostic, Renderer, SourceId, SourceMap};
+
+/// How `tokenize` prints tokens and diagnostics.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Format {
+    /// `TYPE lexeme literal` on stdout, errors on stderr
+    Text,
+    /// One JSON document per file with a `tokens` and a `diagnostics` array
+    Json,
+    /// One JSON object per line with the file name, tagged `token` or
+    /// `diagnostic`
+    JsonLines,
+}
+
+impl Format {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            "jsonl" => Some(Self::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+/// How errors are printed in the text format. Defaults to `Human` when
+/// stderr is a terminal and to `Short` otherwise, so the CodeCrafters tests
+/// see the format they expect.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum ErrorFormat {
+    /// The source line with the error underlined, plus notes and help
+    Human,
+    /// `[line N] Error: message`, as the CodeCrafters tests expect
+    Short,
+}
+
+impl ErrorFormat {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "human" => Some(Self::Human),
+            "short" => Some(Self::Short),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    format: Format,
+    error_format: ErrorFormat,
+    renderer: Renderer,
+    // prefix short errors with the file name
+    show_name: bool,
+}
+
+/// The name a file is shown with, `<stdin>` for `-`.
+fn display_name(filename: &str) -> &str {
+    match filename {
+        "-" => "<stdin>",
+        _ => filename,
+    }
+}
+
+/// Reads a source file, or `stdin` for `-`. On failure returns the message
+/// to print and the exit code to use.
+fn read_source(filename: &str, mut stdin: impl Read) -> Result<String, (String, i32)> {
+    let read = if filename == "-" {
+        let mut bytes = Vec::new();
+        stdin.read_to_end(&mut bytes).map(|_| bytes)
+    } else {
+        fs::read(filename)
+    };
+    // EX_NOINPUT
+    let name = display_name(filename);
+    let bytes = read.map_err(|error| (format!("Failed to read file {name}: {error}"), 66))?;
+
+    String::from_utf8(bytes).map_err(|error| {
+        let offset = error.utf8_error().valid_up_to();
+        // EX_DATAERR
+        let message = format!("{name} is not valid UTF-8 (invalid byte at offset {offset})");
+        (message, 65)
+    })
+}
+
+/// Prints the tokens and diagnostics of one file. Returns whether there
+/// were any diagnostics.
+fn tokenize(sources: &SourceMap, id: SourceId, options: &Options) -> bool {
+    let mut has_error = false;
+    let mut items = Vec::new();
+    let name = sources.file(id).name();
+
+    for result in sources.scanner(id) {
+        has_error |= result.is_err();
+        match (options.format, result) {
+            (Format::Text, Ok(token)) => println!("{token}"),
+            (Format::Text, Err(error)) => match options.error_format {
+                ErrorFormat::Human => {
+                    let diagnostic = Diagnostic::from(&error);
+                    eprintln!("{}", options.renderer.render(&diagnostic, sources))
+                }
+                ErrorFormat::Short if options.show_name => {
+                    eprintln!("{name}: [line {}] Error: {}", error.line(), error)
+                }
+                ErrorFormat::Short => eprintln!("[line {}] Error: {}", error.line(), error),
+            },
+            (Format::Json, item) => items.push(item),
+            (Format::JsonLines, item) => println!("{}", json::file_line(name, &item)),
+        }
+    }
+
+    if options.format == Format::Json {
+        println!("{}", json::file_record(name, &items));
+    }
+
+    has_error
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() < 3 {
-        writeln!(io::stderr(), "Usage: {} tokenize <filename>", args[0]).unwrap();
-        return;
+    let usage = format!(
+        "Usage: {0} tokenize [--format text|json|jsonl] [--error-format human|short] \
+         <filename>... ('-' reads stdin)\n       {0} explain <code>",
+        args[0]
+    );
+
+    let mut format = Format::Text;
+    let mut error_format = None;
+    let mut positional = Vec::new();
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || value.or_else(|| rest.next().map(String::as_str));
+        let valid = match flag {
+            "--format" => value().and_then(Format::parse).map(|f| format = f),
+            "--error-format" => value()
+                .and_then(ErrorFormat::parse)
+                .map(|f| error_format = Some(f)),
+            _ => {
+                positional.push(arg.as_str());
+                continue;
+            }
+        };
+        if valid.is_none() {
+            eprintln!("{usage}");
+            exit(64)
+        }
     }
 
-    let command = &args[1];
-    let filename = &args[2];
+    let [command, filenames @ ..] = positional.as_slice() else {
+        eprintln!("{usage}");
+        return;
+    };
+    if filenames.is_empty() {
+        eprintln!("{usage}");
+        return;
+    }
 
-    match command.as_str() {
+    match *command {
+        "explain" => {
+            let code = filenames[0];
+            match explain(code) {
+                Some(text) => print!("{text}"),
+                None => {
+                    eprintln!("Unknown error code: {code}");
+                    exit(64)
+                }
+            }
+        }
         "tokenize" => {
             // You can use print statements as follows for debugging, they'll be visible when running tests.
-            writeln!(io::stderr(), "Logs from your program will appear here!").unwrap();
+            eprintln!("Logs from your program will appear here!");
 
-            let file_contents = fs::read_to_string(filename).unwrap_or_else(|_| {
-                writeln!(io::stderr(), "Failed to read file {}", filename).unwrap();
-                String::new()
-            });
+            // a JSON document per file would not add up to one document
+            if format == Format::Json && filenames.len() > 1 {
+                eprintln!("--format json takes a single file, use --format jsonl for several");
+                exit(64)
+            }
+            if filenames
+                .iter()
+                .filter(|&&filename| filename == "-")
+                .count()
+                > 1
+            {
+                eprintln!("stdin ('-') can only be read once");
+                exit(64)
+            }
 
-            let mut scanner = Scanner::from(file_contents.as_str());
-            let tokens = match scanner.scan_tokens() {
-                Err(tokens) => tokens,
-                Ok(tokens) => tokens,
-            };
+            // Read everything up front so a missing file doesn't leave half
+            // of the output behind.
+            let mut sources = SourceMap::new();
+            for &filename in filenames {
+                match read_source(filename, io::stdin()) {
+                    Ok(contents) => sources.add(display_name(filename), contents),
+                    Err((message, code)) => {
+                        eprintln!("{message}");
+                        exit(code)
+                    }
+                };
+            }
 
-            for token in tokens {
-                println!("{token}");
+            let terminal = io::stderr().is_terminal();
+            let error_format = error_format.unwrap_or(match terminal {
+                true => ErrorFormat::Human,
+                false => ErrorFormat::Short,
+            });
+            let colour = terminal && env::var_os("NO_COLOR").is_none();
+            let options = Options {
+                format,
+                error_format,
+                renderer: Renderer::new().with_colour(colour),
+                show_name: filenames.len() > 1,
+            };
+            let mut has_error = false;
+            for (id, _) in sources.files() {
+                has_error |= tokenize(&sources, id, &options);
             }
 
-            if scanner.has_error {
+            if has_error {
+                if format == Format::Text && error_format == ErrorFormat::Human {
+                    eprintln!(
+                        "For more information about an error, try `{} explain <code>`.",
+                        args[0]
+                    );
+                }
                 exit(65)
             }
         }
         _ => {
-            writeln!(io::stderr(), "Unknown command: {}", command).unwrap();
-            return;
+            eprintln!("Unknown command: {}", command);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_source() {
+        let stdin = "print 1;".as_bytes();
+        assert_eq!(read_source("-", stdin), Ok("print 1;".to_string()));
+
+        let path = env::temp_dir().join(format!("read-source-{}.lox", std::process::id()));
+        fs::write(&path, "var a;").unwrap();
+        let filename = path.to_str().unwrap();
+        assert_eq!(read_source(filename, io::empty()), Ok("var a;".to_string()));
+
+        fs::write(&path, b"var \xe9;").unwrap();
+        assert_eq!(
+            read_source(filename, io::empty()),
+            Err((
+                format!("{filename} is not valid UTF-8 (invalid byte at offset 4)"),
+                65
+            ))
+        );
+        assert_eq!(
+            read_source("-", &b"print \"\xff\";"[..]),
+            Err((
+                "<stdin> is not valid UTF-8 (invalid byte at offset 7)".to_string(),
+                65
+            ))
+        );
+
+        fs::remove_file(&path).unwrap();
+        let (message, code) = read_source(filename, io::empty()).unwrap_err();
+        assert!(message.starts_with(&format!("Failed to read file {filename}: ")));
+        assert_eq!(code, 66);
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+use bytes::Bytes;
+
+use crate::syntax::dialect::Dialect;
+use crate::syntax::error::ScanError;
+use crate::syntax::incremental::{lookahead_fits, scan_start};
+use crate::syntax::scanner::Scanner;
+use crate::syntax::span::{Position, Span};
+use crate::syntax::token::Token;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reads a source in chunks of at most 64 KiB, see
+/// [`ChunkedScanner::from_reader`].
+#[derive(Debug)]
+pub struct ReadChunks<R> {
+    reader: R,
+}
+
+impl<R: Read> Iterator for ReadChunks<R> {
+    type Item = io::Result<Bytes>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            match self.reader.read(&mut chunk) {
+                Ok(0) => return None,
+                Ok(read) => {
+                    chunk.truncate(read);
+                    return Some(Ok(Bytes::from(chunk)));
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+pub type ByteChunks<C> = std::iter::Map<C, fn(Bytes) -> io::Result<Bytes>>;
+
+/// Scans a source that arrives in chunks, such as a file read piece by
+/// piece, without holding all of it in memory.
+///
+/// Only the text of tokens that are not complete yet is kept between
+/// chunks. A token is complete once the scanner has seen the two characters
+/// following it, so UTF-8 sequences and tokens may straddle chunk
+/// boundaries. Tokens and errors are the same as [`Scanner`] yields for the
+/// whole source, but owned. Reading stops at the first I/O error, and
+/// invalid UTF-8 is reported as an `io::ErrorKind::InvalidData` error.
+///
+/// ```
+/// use bytes::Bytes;
+/// use interpreter_starter_rust::{ChunkedScanner, TokenType};
+///
+/// let chunks = [Bytes::from("print \"hel"), Bytes::from("lo\";")];
+/// let kinds: Vec<_> = ChunkedScanner::from_bytes(chunks)
+///     .map(|item| item.unwrap().unwrap().kind())
+///     .collect();
+///
+/// assert_eq!(
+///     kinds,
+///     [TokenType::Print, TokenType::String, TokenType::Semicolon, TokenType::Eof]
+/// );
+/// ```
+#[derive(Debug)]
+pub struct ChunkedScanner<'d, I> {
+    chunks: I,
+    dialect: &'d Dialect,
+    keep_trivia: bool,
+    buffer: String,      // text not scanned for good yet
+    incomplete: Vec<u8>, // a UTF-8 sequence cut off by the end of a chunk
+    base: usize,         // stream offset of the start of `buffer`
+    position: Position,  // position of the start of `buffer`
+    resumed: bool,       // whether the preamble has been scanned
+    rescan_at: usize,    // buffer length worth scanning again
+    ready: VecDeque<Result<Token<'static>, ScanError>>,
+    done: bool,
+}
+
+impl<I: Iterator<Item = io::Result<Bytes>>> ChunkedScanner<'static, I> {
+    pub fn new(chunks: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            chunks: chunks.into_iter(),
+            dialect: Dialect::lox_ref(),
+            keep_trivia: false,
+            buffer: String::new(),
+            incomplete: vec![],
+            base: 0,
+            position: Position::default(),
+            resumed: false,
+            rescan_at: 0,
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+impl<R: Read> ChunkedScanner<'static, ReadChunks<R>> {
+    pub fn from_reader(reader: R) -> Self {
+        Self::new(ReadChunks { reader })
+    }
+}
+
+impl<C: Iterator<Item = Bytes>> ChunkedScanner<'static, ByteChunks<C>> {
+    pub fn from_bytes(chunks: impl IntoIterator<IntoIter = C>) -> Self {
+        Self::new(chunks.into_iter().map(Ok as fn(Bytes) -> io::Result<Bytes>))
+    }
+}
+
+impl<'d, I: Iterator<Item = io::Result<Bytes>>> ChunkedScanner<'d, I> {
+    /// See [`Scanner::with_dialect`].
+    pub fn with_dialect<'e>(self, dialect: &'e Dialect) -> ChunkedScanner<'e, I> {
+        ChunkedScanner { dialect, ..self }
+    }
+
+    /// See [`Scanner::with_trivia`].
+    pub fn with_trivia(mut self) -> Self {
+        self.keep_trivia = true;
+        self
+    }
+
+    fn invalid_utf8(&self) -> io::Error {
+        let offset = self.base + self.buffer.len();
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("source is not valid UTF-8 (invalid byte at offset {offset})"),
+        )
+    }
+
+    /// Appends the text of a chunk to the buffer.
+    fn decode(&mut self, mut bytes: &[u8]) -> io::Result<()> {
+        // finish the character the previous chunk ended in
+        while !self.incomplete.is_empty() {
+            let Some((&byte, rest)) = bytes.split_first() else {
+                return Ok(());
+            };
+            bytes = rest;
+            self.incomplete.push(byte);
+            match std::str::from_utf8(&self.incomplete) {
+                Ok(c) => {
+                    self.buffer.push_str(c);
+                    self.incomplete.clear();
+                }
+                Err(error) if error.error_len().is_some() => return Err(self.invalid_utf8()),
+                Err(_) => {}
+            }
+        }
+
+        match std::str::from_utf8(bytes) {
+            Ok(text) => self.buffer.push_str(text),
+            Err(error) => {
+                let (valid, rest) = bytes.split_at(error.valid_up_to());
+                self.buffer.push_str(std::str::from_utf8(valid).unwrap());
+                if error.error_len().is_some() {
+                    return Err(self.invalid_utf8());
+                }
+                self.incomplete.extend_from_slice(rest);
+            }
+        }
+        Ok(())
+    }
+
+    /// Scans the buffer and queues the items that more text can no longer
+    /// change, or all of them at the end of the source. The text of those
+    /// items is dropped from the buffer.
+    fn scan(&mut self, at_end: bool) {
+        let mut scanner = Scanner::from(self.buffer.as_str()).with_dialect(self.dialect);
+        if self.keep_trivia {
+            scanner = scanner.with_trivia();
+        }
+        let origin = Span::new(0, 0, self.position, self.position);
+        if self.resumed {
+            scanner = scanner.resume_at(origin);
+        }
+
+        let mut items = vec![];
+        loop {
+            let outside = scanner.interpolation_depth() == 0;
+            match scanner.next() {
+                Some(item) => items.push((outside, item)),
+                None => break,
+            }
+        }
+
+        // everything up to the last point scanning can restart at, see
+        // `Scanner::relex`
+        let restart = if at_end {
+            None
+        } else {
+            (1..items.len())
+                .rev()
+                .find_map(|index| match &items[index] {
+                    (true, Ok(token)) if items[index - 1].1.is_ok() => {
+                        let at = scan_start(token);
+                        lookahead_fits(&self.buffer, at.start, self.buffer.len())
+                            .then_some((index, at))
+                    }
+                    _ => None,
+                })
+        };
+        let commit = match restart {
+            Some((index, _)) => index,
+            None if at_end => items.len(),
+            None => 0,
+        };
+
+        let to = Span::new(self.base, self.base, self.position, self.position);
+        for (_, item) in items.drain(..commit) {
+            self.ready.push_back(match item {
+                Ok(token) => Ok(token.into_owned().relocated(origin, to)),
+                Err(mut error) => {
+                    let span = error.span_mut();
+                    *span = span.relocated(origin, to);
+                    Err(error)
+                }
+            });
+        }
+        drop(items);
+
+        match restart {
+            Some((_, at)) => {
+                self.buffer.drain(..at.start);
+                self.base += at.start;
+                self.position = at.start_pos;
+                self.resumed = true;
+                self.rescan_at = 0;
+            }
+            // wait for twice the text before scanning a long token again
+            None => self.rescan_at = self.buffer.len() * 2,
+        }
+    }
+}
+
+impl<I: Iterator<Item = io::Result<Bytes>>> Iterator for ChunkedScanner<'_, I> {
+    type Item = io::Result<Result<Token<'static>, ScanError>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.ready.pop_front() {
+                return Some(Ok(item));
+            }
+            if self.done {
+                return None;
+            }
+
+            match self.chunks.next() {
+                Some(Ok(chunk)) => {
+                    if let Err(error) = self.decode(&chunk) {
+                        self.done = true;
+                        return Some(Err(error));
+                    }
+                    if self.buffer.len() >= self.rescan_at {
+                        self.scan(false);
+                    }
+                }
+                Some(Err(error)) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+                None => {
+                    self.done = true;
+                    if !self.incomplete.is_empty() {
+                        return Some(Err(self.invalid_utf8()));
+                    }
+                    self.scan(true);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCES: &[&str] = &[
+        "",
+        "\u{feff}#!/usr/bin/env lox\nprint 1;",
+        "var é = \"ünïcödé ☺\"; // ♣ comment\r\nprint é;",
+        "1.5 1. .5 0x1f 1_000 1e10 6.02e+23 123abc",
+        "\"total: ${a + \"nested ${b}\"} and ${ {c} }!\"",
+        "/* outer /* inner */ still */ x /= y ** 2",
+        "\"bad \\q escape ${ x \" } \" y = 1;",
+        "@ # $ ^ a",
+        "\"unterminated ${ x",
+        "/* unterminated",
+        "\"unterminated",
+    ];
+
+    fn full_scan(source: &str, trivia: bool) -> Vec<Result<Token<'static>, ScanError>> {
+        let scanner = Scanner::from(source);
+        let scanner = if trivia {
+            scanner.with_trivia()
+        } else {
+            scanner
+        };
+        scanner.map(|item| item.map(Token::into_owned)).collect()
+    }
+
+    fn chunks(source: &str, size: usize) -> Vec<Bytes> {
+        source
+            .as_bytes()
+            .chunks(size)
+            .map(Bytes::copy_from_slice)
+            .collect()
+    }
+
+    #[test]
+    fn test_chunked_matches_full_scan() {
+        for source in SOURCES {
+            for size in 1..=9 {
+                for trivia in [false, true] {
+                    let scanner = ChunkedScanner::from_bytes(chunks(source, size));
+                    let scanner = if trivia {
+                        scanner.with_trivia()
+                    } else {
+                        scanner
+                    };
+                    let items: Vec<_> = scanner.map(Result::unwrap).collect();
+
+                    assert_eq!(
+                        items,
+                        full_scan(source, trivia),
+                        "{source:?} in chunks of {size}, trivia: {trivia}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunked_memory_is_bounded() {
+        let line = "var line = \"some text ${n} more\"; /* comment */ print line;\n";
+        let source = line.repeat(20_000);
+        let mut scanner = ChunkedScanner::from_bytes(chunks(&source, 4096));
+
+        let mut count = 0;
+        let mut largest_buffer = 0;
+        while let Some(item) = scanner.next() {
+            item.unwrap().unwrap();
+            count += 1;
+            largest_buffer = largest_buffer.max(scanner.buffer.len());
+        }
+
+        assert_eq!(count, full_scan(&source, false).len());
+        assert!(largest_buffer < 2 * 4096, "{largest_buffer}");
+    }
+
+    #[test]
+    fn test_chunked_from_reader() {
+        /// Hands out a few bytes per read, like a pipe might.
+        struct Trickle<'a>(&'a [u8]);
+
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let read = self.0.len().min(buf.len()).min(3);
+                buf[..read].copy_from_slice(&self.0[..read]);
+                self.0 = &self.0[read..];
+                Ok(read)
+            }
+        }
+
+        let source = SOURCES[2];
+        let items: Vec<_> = ChunkedScanner::from_reader(Trickle(source.as_bytes()))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(items, full_scan(source, false));
+    }
+
+    #[test]
+    fn test_chunked_invalid_utf8() {
+        let invalid = [Bytes::from("print 1;\n"), Bytes::from(&b"x \xff y"[..])];
+        let items: Vec<_> = ChunkedScanner::from_bytes(invalid).collect();
+        let error = items.last().unwrap().as_ref().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("offset 11"), "{error}");
+
+        // a character cut off by the end of the source
+        let truncated = [Bytes::from("a"), Bytes::from(&"é".as_bytes()[..1])];
+        let items: Vec<_> = ChunkedScanner::from_bytes(truncated).collect();
+        assert!(items.last().unwrap().is_err());
+        assert!(items.iter().all(|item| !matches!(item, Ok(Err(_)))));
+    }
+}
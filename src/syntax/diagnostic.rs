@@ -0,0 +1,396 @@
+use std::fmt::Write;
+
+use crate::syntax::error::ScanError;
+use crate::syntax::source::SourceMap;
+use crate::syntax::span::Span;
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// A span in the source with a short message pointing at it. Primary labels
+/// mark where the error is and are underlined with `^`, secondary labels add
+/// context and are underlined with `-`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+/// An error message with the source locations and advice needed to present
+/// it to a person.
+///
+/// ```
+/// use interpreter_starter_rust::{Diagnostic, Renderer, SourceMap};
+///
+/// let mut sources = SourceMap::new();
+/// let id = sources.add("main.lox", "var s = \"abc;");
+/// let (_, errors) = sources.scanner(id).scan_tokens();
+///
+/// let report = Renderer::new().render(&Diagnostic::from(&errors[0]), &sources);
+/// assert!(report.contains("1 | var s = \"abc;"));
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    code: Option<&'static str>,
+    message: String,
+    labels: Vec<Label>,
+    notes: Vec<String>,
+    help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            code: None,
+            message: message.into(),
+            labels: vec![],
+            notes: vec![],
+            help: vec![],
+        }
+    }
+
+    /// Tags the diagnostic with a stable error code, see
+    /// [`ScanError::code`].
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: true,
+        });
+        self
+    }
+
+    pub fn with_secondary_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn code(&self) -> Option<&'static str> {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn labels(&self) -> &[Label] {
+        &self.labels
+    }
+
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+
+    pub fn help(&self) -> &[String] {
+        &self.help
+    }
+}
+
+impl From<&ScanError> for Diagnostic {
+    fn from(error: &ScanError) -> Self {
+        let diagnostic = Diagnostic::error(error.to_string()).with_code(error.code());
+        let span = error.span();
+
+        match error {
+            ScanError::UnexpectedCharacter { character, .. } => {
+                let diagnostic = diagnostic.with_label(span, "not valid in Lox source");
+                if character.is_ascii_graphic() {
+                    diagnostic
+                } else {
+                    diagnostic.with_note(format!(
+                        "the character is U+{:04X} `{}`",
+                        *character as u32,
+                        character.escape_debug()
+                    ))
+                }
+            }
+            ScanError::UnterminatedString { .. } => diagnostic
+                .with_label(span.shrink_to_start(), "string starts here")
+                .with_secondary_label(span.shrink_to_end(), "input ends before the string does")
+                .with_help("add a closing `\"`"),
+            ScanError::UnterminatedBlockComment { .. } => diagnostic
+                .with_label(span, "comment starts here")
+                .with_note("block comments nest, every `/*` needs its own `*/`"),
+            ScanError::InvalidEscape { .. } => diagnostic
+                .with_label(span, "unknown escape sequence")
+                .with_help(
+                    "valid escapes are `\\\"`, `\\\\`, `\\$`, `\\n`, `\\t`, `\\r`, `\\0` \
+                     and `\\u{...}` with 1 to 6 hex digits",
+                ),
+            ScanError::UnterminatedInterpolation { .. } => diagnostic
+                .with_label(span, "`${` is never closed")
+                .with_help("add a `}` to end the interpolated expression"),
+            ScanError::MalformedNumber { reason, .. } => diagnostic.with_label(span, *reason),
+            ScanError::IntegerOverflow { .. } => diagnostic
+                .with_label(span, "does not fit in a 64-bit integer")
+                .with_help("add a fractional part, e.g. `.0`, to make it a float"),
+            ScanError::ConfusableIdentifier { .. } => diagnostic
+                .with_label(span, "mixes characters from several scripts")
+                .with_note(
+                    "some of its letters look like those of another script, so the name may \
+                     not be what it seems",
+                ),
+            ScanError::DisabledToken { .. } => {
+                diagnostic.with_label(span, "disabled in this dialect")
+            }
+        }
+    }
+}
+
+/// Formats diagnostics the way a compiler would: the message, the file and
+/// position, and the source lines with the labelled spans underlined.
+///
+/// ```text
+/// error[L0001]: Unterminated string.
+///  --> main.lox:1:9
+///   |
+/// 1 | var s = "abc;
+///   |         ^ string starts here
+///   |              - input ends before the string does
+///   |
+///   = help: add a closing `"`
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Renderer {
+    colour: bool,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Highlights the output with ANSI colours.
+    pub fn with_colour(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
+    }
+
+    fn paint(&self, colour: &str, text: &str) -> String {
+        if self.colour {
+            format!("{colour}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic, sources: &SourceMap) -> String {
+        let mut out = String::new();
+        let severity = match diagnostic.code {
+            Some(code) => format!("error[{code}]"),
+            None => "error".to_string(),
+        };
+        writeln!(
+            out,
+            "{}{}",
+            self.paint(RED, &severity),
+            self.paint(BOLD, &format!(": {}", diagnostic.message))
+        )
+        .unwrap();
+
+        // the primary label's file first, then in source order
+        let mut labels: Vec<&Label> = diagnostic.labels.iter().collect();
+        let primary = labels.iter().find(|label| label.primary).or(labels.first());
+        let primary_pos = primary.map(|label| label.span.start_pos);
+        let primary_source = primary.map(|label| label.span.source);
+        labels.sort_by_key(|label| {
+            let span = label.span;
+            let other_file = Some(span.source) != primary_source;
+            (
+                other_file,
+                span.source,
+                span.start_pos.line,
+                span.start_pos.column,
+            )
+        });
+
+        let width = labels
+            .iter()
+            .map(|label| label.span.start_pos.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let gutter = self.paint(BLUE, &format!("{:width$} |", ""));
+
+        let mut previous: Option<Span> = None;
+        for label in &labels {
+            let span = label.span;
+            let line = span.start_pos.line;
+            let same_file = previous.is_some_and(|p| p.source == span.source);
+
+            if !same_file {
+                let (arrow, position) = match (previous, primary_pos) {
+                    (None, Some(position)) => ("-->", position),
+                    _ => (":::", span.start_pos),
+                };
+                writeln!(
+                    out,
+                    "{:width$}{} {}:{}",
+                    "",
+                    self.paint(BLUE, arrow),
+                    sources.name(span),
+                    position
+                )
+                .unwrap();
+                writeln!(out, "{gutter}").unwrap();
+            }
+
+            let text = sources.line_text(span);
+            let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+            if !same_file || previous.is_some_and(|p| p.start_pos.line != line) {
+                if same_file && previous.is_some_and(|p| p.start_pos.line.get() + 1 < line.get()) {
+                    writeln!(out, "{}", self.paint(BLUE, "...")).unwrap();
+                }
+                let number = self.paint(BLUE, &format!("{line:>width$} |"));
+                writeln!(out, "{}", format!("{number} {text}").trim_end()).unwrap();
+            }
+
+            // keep tabs so the underline lines up with the text above it
+            let skip = span.start_pos.column.get() - 1;
+            let padding: String = text
+                .chars()
+                .take(skip)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let length = if span.end_pos.line == line {
+                span.end_pos.column.get() - span.start_pos.column.get()
+            } else {
+                text.chars().count().saturating_sub(skip)
+            };
+            let (marker, colour) = if label.primary {
+                ('^', RED)
+            } else {
+                ('-', BLUE)
+            };
+            let underline = marker.to_string().repeat(length.max(1));
+            writeln!(
+                out,
+                "{gutter} {padding}{}",
+                self.paint(colour, &format!("{underline} {}", label.message))
+            )
+            .unwrap();
+
+            previous = Some(span);
+        }
+
+        if !diagnostic.notes.is_empty() || !diagnostic.help.is_empty() {
+            if !labels.is_empty() {
+                writeln!(out, "{gutter}").unwrap();
+            }
+            let notes = diagnostic.notes.iter().map(|note| ("note", note));
+            let help = diagnostic.help.iter().map(|help| ("help", help));
+            for (kind, text) in notes.chain(help) {
+                let equals = self.paint(BLUE, &format!("{:width$} =", ""));
+                writeln!(out, "{equals} {}: {text}", self.paint(BOLD, kind)).unwrap();
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str) -> String {
+        let mut sources = SourceMap::new();
+        let id = sources.add("main.lox", source);
+        let (_, errors) = sources.scanner(id).scan_tokens();
+        errors
+            .iter()
+            .map(|error| Renderer::new().render(&Diagnostic::from(error), &sources))
+            .collect()
+    }
+
+    #[test]
+    fn test_render_unexpected_character() {
+        assert_eq!(
+            render("print 1;\n\tprint @;"),
+            concat!(
+                "error[L0002]: Unexpected character: @\n",
+                " --> main.lox:2:8\n",
+                "  |\n",
+                "2 | \tprint @;\n",
+                "  | \t      ^ not valid in Lox source\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_secondary_label_on_later_line() {
+        let report = render(&format!("var s = \"abc{}\n", "\n".repeat(9)));
+
+        assert_eq!(
+            report,
+            concat!(
+                "error[L0001]: Unterminated string.\n",
+                "  --> main.lox:1:9\n",
+                "   |\n",
+                " 1 | var s = \"abc\n",
+                "   |         ^ string starts here\n",
+                "...\n",
+                "11 |\n",
+                "   | - input ends before the string does\n",
+                "   |\n",
+                "   = help: add a closing `\"`\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_notes_and_colour() {
+        let mut sources = SourceMap::new();
+        let id = sources.add("main.lox", "print 1_;");
+        let (_, errors) = sources.scanner(id).scan_tokens();
+        let diagnostic = Diagnostic::from(&errors[0]).with_note("`_` separates digits");
+
+        let plain = Renderer::new().render(&diagnostic, &sources);
+        assert_eq!(
+            plain,
+            concat!(
+                "error[L0006]: Malformed number: trailing underscore.\n",
+                " --> main.lox:1:7\n",
+                "  |\n",
+                "1 | print 1_;\n",
+                "  |       ^^ trailing underscore\n",
+                "  |\n",
+                "  = note: `_` separates digits\n",
+            )
+        );
+
+        let coloured = Renderer::new()
+            .with_colour(true)
+            .render(&diagnostic, &sources);
+        assert!(coloured.contains("\x1b[1;31m^^ trailing underscore\x1b[0m"));
+        assert_eq!(
+            coloured
+                .replace(RED, "")
+                .replace(BLUE, "")
+                .replace(BOLD, "")
+                .replace(RESET, ""),
+            plain
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use crate::syntax::token::TokenType;
+use crate::syntax::unicode;
+
+static LOX: OnceLock<Dialect> = OnceLock::new();
+
+/// The keywords and tokens a [`Scanner`](crate::Scanner) recognizes.
+///
+/// The default is plain Lox. Embedders can derive their own dialect from it,
+/// for example to reserve words for future use or to disable `class` in a
+/// sandboxed profile:
+///
+/// ```
+/// use interpreter_starter_rust::{Dialect, Scanner, TokenType};
+///
+/// let dialect = Dialect::lox().reserve("import").disable(TokenType::Class);
+/// let (_, diagnostics) = Scanner::from("class import").with_dialect(&dialect).scan_tokens();
+/// assert_eq!(diagnostics.len(), 1);
+/// ```
+///
+/// Keywords are looked up when an identifier has been scanned, so every
+/// spelling must be an identifier. Operators can be given a spelled out
+/// alternative such as `not` for `!`, but their symbols can not be changed,
+/// only disabled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dialect {
+    // Changes to the Lox keywords, `None` turns a keyword back into an
+    // identifier. Only consulted when non-empty so plain Lox never hashes.
+    keywords: HashMap<String, Option<TokenType>>,
+    disabled: HashSet<TokenType>,
+}
+
+impl Dialect {
+    pub fn lox() -> Self {
+        Self {
+            keywords: HashMap::new(),
+            disabled: HashSet::new(),
+        }
+    }
+
+    /// A shared instance of [`Dialect::lox`], used by scanners that were not
+    /// given a dialect.
+    pub(crate) fn lox_ref() -> &'static Self {
+        LOX.get_or_init(Self::lox)
+    }
+
+    /// Scans `spelling` as `token_type` instead of as an identifier. The
+    /// token type does not have to be a keyword, `with_keyword("not",
+    /// TokenType::Bang)` adds a spelled out alternative to `!`.
+    ///
+    /// # Panics
+    ///
+    /// If `spelling` is not an identifier, like `^`.
+    pub fn with_keyword(mut self, spelling: impl Into<String>, token_type: TokenType) -> Self {
+        let spelling = spelling.into();
+        assert_identifier(&spelling);
+        self.keywords.insert(spelling, Some(token_type));
+        self
+    }
+
+    /// Scans `spelling` as a plain identifier.
+    ///
+    /// # Panics
+    ///
+    /// If `spelling` is not an identifier.
+    pub fn without_keyword(mut self, spelling: &str) -> Self {
+        assert_identifier(spelling);
+        self.keywords.insert(spelling.to_string(), None);
+        self
+    }
+
+    /// Changes the spelling of a keyword, e.g. `var` to `let`.
+    ///
+    /// # Panics
+    ///
+    /// If either spelling is not an identifier, so operators such as `**`
+    /// can not be renamed.
+    pub fn rename_keyword(self, from: &str, to: impl Into<String>) -> Self {
+        assert_identifier(from);
+        match self.keyword(from) {
+            Some(token_type) => self.without_keyword(from).with_keyword(to, token_type),
+            None => self,
+        }
+    }
+
+    /// Keeps `spelling` from being used as an identifier without giving it
+    /// a meaning yet. It scans as `TokenType::Reserved`.
+    pub fn reserve(self, spelling: impl Into<String>) -> Self {
+        self.with_keyword(spelling, TokenType::Reserved)
+    }
+
+    /// Reports every token of `token_type` as an error, however it is
+    /// spelled.
+    pub fn disable(mut self, token_type: TokenType) -> Self {
+        self.disabled.insert(token_type);
+        self
+    }
+
+    pub fn keyword(&self, spelling: &str) -> Option<TokenType> {
+        if !self.keywords.is_empty() {
+            if let Some(token_type) = self.keywords.get(spelling) {
+                return *token_type;
+            }
+        }
+
+        lox_keyword(spelling)
+    }
+
+    pub fn is_disabled(&self, token_type: TokenType) -> bool {
+        !self.disabled.is_empty() && self.disabled.contains(&token_type)
+    }
+}
+
+/// Panics unless `spelling` scans as a single identifier, the only tokens
+/// a dialect can respell.
+fn assert_identifier(spelling: &str) {
+    let mut chars = spelling.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|c| unicode::is_xid_start(c) || c == '_')
+        && chars.all(unicode::is_xid_continue);
+    assert!(
+        is_identifier,
+        "dialect spellings must be identifiers, not {spelling:?}; \
+         operators can only be disabled"
+    );
+}
+
+/// Looks up a Lox keyword without hashing: the length and first byte (and
+/// the second byte where those collide) select the single candidate, which
+/// is then compared in full.
+pub(crate) fn lox_keyword(spelling: &str) -> Option<TokenType> {
+    let bytes = spelling.as_bytes();
+    let (keyword, token_type) = match (bytes.len(), *bytes.first()?) {
+        (2, b'i') => ("if", TokenType::If),
+        (2, b'o') => ("or", TokenType::Or),
+        (3, b'a') => ("and", TokenType::And),
+        (3, b'f') if bytes[1] == b'o' => ("for", TokenType::For),
+        (3, b'f') => ("fun", TokenType::Fun),
+        (3, b'n') => ("nil", TokenType::Nil),
+        (3, b'v') => ("var", TokenType::Var),
+        (4, b'e') => ("else", TokenType::Else),
+        (4, b't') if bytes[1] == b'h' => ("this", TokenType::This),
+        (4, b't') => ("true", TokenType::True),
+        (5, b'c') => ("class", TokenType::Class),
+        (5, b'f') => ("false", TokenType::False),
+        (5, b'p') => ("print", TokenType::Print),
+        (5, b's') => ("super", TokenType::Super),
+        (5, b'w') => ("while", TokenType::While),
+        (6, b'r') => ("return", TokenType::Return),
+        _ => return None,
+    };
+
+    (spelling == keyword).then_some(token_type)
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self::lox()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::scanner::Scanner;
+
+    #[test]
+    fn test_lox_keywords() {
+        let dialect = Dialect::lox();
+
+        assert_eq!(dialect.keyword("while"), Some(TokenType::While));
+        assert_eq!(dialect.keyword("let"), None);
+        assert!(!dialect.is_disabled(TokenType::Class));
+        assert_eq!(&dialect, Dialect::lox_ref());
+    }
+
+    #[test]
+    fn test_lox_keyword_lookup() {
+        let keywords = [
+            ("and", TokenType::And),
+            ("class", TokenType::Class),
+            ("else", TokenType::Else),
+            ("false", TokenType::False),
+            ("for", TokenType::For),
+            ("fun", TokenType::Fun),
+            ("if", TokenType::If),
+            ("nil", TokenType::Nil),
+            ("or", TokenType::Or),
+            ("print", TokenType::Print),
+            ("return", TokenType::Return),
+            ("super", TokenType::Super),
+            ("this", TokenType::This),
+            ("true", TokenType::True),
+            ("var", TokenType::Var),
+            ("while", TokenType::While),
+        ];
+        for (spelling, token_type) in keywords {
+            assert_eq!(lox_keyword(spelling), Some(token_type));
+        }
+
+        for spelling in [
+            "", "a", "fox", "thus", "tru", "trues", "While", "retur", "é",
+        ] {
+            assert_eq!(lox_keyword(spelling), None, "{spelling}");
+        }
+    }
+
+    #[test]
+    fn test_customized_keywords() {
+        let dialect = Dialect::lox()
+            .rename_keyword("var", "let")
+            .reserve("import")
+            .with_keyword("not", TokenType::Bang)
+            .without_keyword("print")
+            .disable(TokenType::Class);
+
+        assert_eq!(dialect.keyword("var"), None);
+        assert_eq!(dialect.keyword("let"), Some(TokenType::Var));
+        assert_eq!(dialect.keyword("import"), Some(TokenType::Reserved));
+        assert_eq!(dialect.keyword("not"), Some(TokenType::Bang));
+        assert_eq!(dialect.keyword("print"), None);
+        assert_eq!(dialect.keyword("class"), Some(TokenType::Class));
+        assert!(dialect.is_disabled(TokenType::Class));
+    }
+
+    #[test]
+    fn test_spelled_out_operator() {
+        let dialect = Dialect::lox().with_keyword("pow", TokenType::StarStar);
+        assert_eq!(dialect.keyword("pow"), Some(TokenType::StarStar));
+
+        let (tokens, _) = Scanner::from("2 pow 3")
+            .with_dialect(&dialect)
+            .scan_tokens();
+        assert_eq!(tokens[1].to_string(), "STAR_STAR pow null");
+    }
+
+    #[test]
+    #[should_panic(expected = "operators can only be disabled")]
+    fn test_operator_symbols_can_not_be_respelled() {
+        let _ = Dialect::lox().with_keyword("^", TokenType::StarStar);
+    }
+
+    #[test]
+    #[should_panic(expected = "operators can only be disabled")]
+    fn test_operators_can_not_be_renamed() {
+        let _ = Dialect::lox().rename_keyword("**", "pow");
+    }
+}
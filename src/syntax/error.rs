@@ -0,0 +1,135 @@
+use std::num::NonZeroUsize;
+
+use thiserror::Error;
+
+use crate::syntax::span::Span;
+
+#[derive(Debug, Error, PartialEq, Clone)]
+pub enum ScanError {
+    #[error("Unexpected character: {character}")]
+    UnexpectedCharacter { character: char, span: Span },
+
+    #[error("Unterminated string.")]
+    UnterminatedString { span: Span },
+
+    #[error("Unterminated block comment.")]
+    UnterminatedBlockComment { span: Span },
+
+    #[error("Invalid escape sequence: {sequence}")]
+    InvalidEscape { sequence: String, span: Span },
+
+    #[error("Unterminated string interpolation.")]
+    UnterminatedInterpolation { span: Span },
+
+    #[error("Malformed number: {reason}.")]
+    MalformedNumber { reason: &'static str, span: Span },
+
+    #[error("Integer literal is too large.")]
+    IntegerOverflow { span: Span },
+
+    #[error("Confusable mixed-script identifier: {identifier}")]
+    ConfusableIdentifier { identifier: String, span: Span },
+
+    #[error("Not allowed in this dialect: {lexeme}")]
+    DisabledToken { lexeme: String, span: Span },
+}
+
+impl ScanError {
+    /// The source text the error applies to.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::UnexpectedCharacter { span, .. }
+            | Self::UnterminatedString { span }
+            | Self::UnterminatedBlockComment { span }
+            | Self::InvalidEscape { span, .. }
+            | Self::UnterminatedInterpolation { span }
+            | Self::MalformedNumber { span, .. }
+            | Self::IntegerOverflow { span }
+            | Self::ConfusableIdentifier { span, .. }
+            | Self::DisabledToken { span, .. } => *span,
+        }
+    }
+
+    pub(crate) fn span_mut(&mut self) -> &mut Span {
+        match self {
+            Self::UnexpectedCharacter { span, .. }
+            | Self::UnterminatedString { span }
+            | Self::UnterminatedBlockComment { span }
+            | Self::InvalidEscape { span, .. }
+            | Self::UnterminatedInterpolation { span }
+            | Self::MalformedNumber { span, .. }
+            | Self::IntegerOverflow { span }
+            | Self::ConfusableIdentifier { span, .. }
+            | Self::DisabledToken { span, .. } => span,
+        }
+    }
+
+    /// A stable identifier for the kind of error, e.g. `L0001`, that
+    /// documentation and tools can refer to. `explain` describes each one.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnterminatedString { .. } => "L0001",
+            Self::UnexpectedCharacter { .. } => "L0002",
+            Self::UnterminatedBlockComment { .. } => "L0003",
+            Self::InvalidEscape { .. } => "L0004",
+            Self::UnterminatedInterpolation { .. } => "L0005",
+            Self::MalformedNumber { .. } => "L0006",
+            Self::IntegerOverflow { .. } => "L0007",
+            Self::ConfusableIdentifier { .. } => "L0008",
+            Self::DisabledToken { .. } => "L0009",
+        }
+    }
+
+    /// The line the error is reported on.
+    ///
+    /// An unterminated string is only noticed at the end of the input, so
+    /// like the reference implementation it is reported on the last line.
+    pub fn line(&self) -> NonZeroUsize {
+        match self {
+            Self::UnterminatedString { span } => span.end_pos.line,
+            _ => self.span().start_pos.line,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::explain::explain;
+
+    #[test]
+    fn test_every_code_is_explained() {
+        let span = Span::default();
+        let errors = [
+            ScanError::UnterminatedString { span },
+            ScanError::UnexpectedCharacter {
+                character: '@',
+                span,
+            },
+            ScanError::UnterminatedBlockComment { span },
+            ScanError::InvalidEscape {
+                sequence: "\\q".to_string(),
+                span,
+            },
+            ScanError::UnterminatedInterpolation { span },
+            ScanError::MalformedNumber {
+                reason: "missing digits",
+                span,
+            },
+            ScanError::IntegerOverflow { span },
+            ScanError::ConfusableIdentifier {
+                identifier: "раypal".to_string(),
+                span,
+            },
+            ScanError::DisabledToken {
+                lexeme: "class".to_string(),
+                span,
+            },
+        ];
+
+        for (index, error) in errors.iter().enumerate() {
+            assert_eq!(error.code(), format!("L{:04}", index + 1));
+            assert!(explain(error.code()).is_some(), "{}", error.code());
+        }
+    }
+}
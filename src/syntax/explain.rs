@@ -0,0 +1,195 @@
+//! Long-form descriptions of the error codes, shown by `explain <code>`.
+
+/// The explanation of an error code such as `L0001`, case insensitive.
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(code))
+        .map(|(_, text)| text.trim_start())
+}
+
+/// Every error code with its explanation, in order.
+pub const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "L0001",
+        r#"
+A string literal is missing its closing `"`.
+
+Strings may span several lines, so the scanner only notices the missing quote
+at the end of the file and reports the error on the last line.
+
+Example:
+
+    print "hello;
+
+Fix: close the string where it was meant to end.
+
+    print "hello";
+"#,
+    ),
+    (
+        "L0002",
+        r#"
+The source contains a character that does not start any Lox token.
+
+Lox has no `@`, `#`, `&` or `|` operators, and characters outside of strings
+and comments must be punctuation, digits, letters or whitespace. Invisible
+characters such as a stray control character or a zero width space are
+reported with their code point.
+
+Example:
+
+    var total = a & b;
+
+Fix: remove the character, use the Lox spelling (`and`, `or`), or move it
+into a string.
+
+    var total = a and b;
+"#,
+    ),
+    (
+        "L0003",
+        r#"
+A block comment was opened with `/*` but never closed.
+
+Block comments nest, so every `/*` inside a comment needs its own `*/`. The
+error points at the outermost opening `/*`.
+
+Example:
+
+    /* outer /* inner */
+    print 1;
+
+Fix: add the missing `*/`.
+
+    /* outer /* inner */ */
+    print 1;
+"#,
+    ),
+    (
+        "L0004",
+        r#"
+A string contains a `\` that does not start a known escape sequence.
+
+The valid escapes are `\"`, `\\`, `\$`, `\n`, `\t`, `\r`, `\0` and
+`\u{...}` with 1 to 6 hex digits naming a Unicode scalar value.
+
+Example:
+
+    print "C:\temp\file";
+
+Fix: escape the backslash itself, or use one of the valid sequences.
+
+    print "C:\\temp\\file";
+"#,
+    ),
+    (
+        "L0005",
+        r#"
+An interpolation `${` inside a string was never closed with `}`.
+
+Everything after `${` is scanned as an expression until the matching `}`,
+after which the string continues.
+
+Example:
+
+    print "total: ${price * count";
+
+Fix: close the interpolated expression, or escape the `$` to print it
+literally.
+
+    print "total: ${price * count}";
+    print "total: \${price}";
+"#,
+    ),
+    (
+        "L0006",
+        r#"
+A number literal is not well formed.
+
+Numbers are decimal digits with an optional fraction and exponent, or
+integers with a `0x`, `0o` or `0b` prefix. `_` may group digits, as in
+`1_000` or `0x_ff`, but a group of digits may not end with one.
+
+Example:
+
+    var big = 1_000_;
+    var mask = 0x;
+    var tiny = 1e;
+
+Fix: complete or remove the offending part.
+
+    var big = 1_000;
+    var mask = 0xff;
+    var tiny = 1e-9;
+"#,
+    ),
+    (
+        "L0007",
+        r#"
+An integer literal does not fit in a 64-bit signed integer.
+
+Integer literals range from 0 to 9223372036854775807.
+
+Example:
+
+    var n = 99999999999999999999;
+
+Fix: use a float literal, which trades precision for range.
+
+    var n = 99999999999999999999.0;
+"#,
+    ),
+    (
+        "L0008",
+        r#"
+An identifier mixes letters from several scripts, and some of them look like
+letters from another script.
+
+Names such as `раypal`, whose first two letters are Cyrillic, read like a
+Latin name but are a different identifier, which can hide bugs or malicious
+code.
+
+Example:
+
+    var pаssword = "x"; // the `а` is Cyrillic
+
+Fix: spell the identifier with letters from a single script.
+
+    var password = "x";
+"#,
+    ),
+    (
+        "L0009",
+        r#"
+The token is not allowed in the dialect the source is being scanned with.
+
+Embedders can disable tokens, for example `class` in a sandboxed profile.
+The token is recognized but reported wherever it appears.
+
+Example, with `class` disabled:
+
+    class Point {}
+
+Fix: rewrite the code without the disabled construct, or scan it with a
+dialect that allows it.
+"#,
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain() {
+        assert!(explain("L0001").unwrap().starts_with("A string literal"));
+        assert_eq!(explain("l0001"), explain("L0001"));
+        assert_eq!(explain("L9999"), None);
+
+        for (index, (code, text)) in EXPLANATIONS.iter().enumerate() {
+            assert_eq!(*code, format!("L{:04}", index + 1));
+            assert!(text.contains("Example") && text.contains("Fix"), "{code}");
+        }
+    }
+}
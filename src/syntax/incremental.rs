@@ -0,0 +1,352 @@
+use std::ops::Range;
+
+use crate::syntax::error::ScanError;
+use crate::syntax::scanner::Scanner;
+use crate::syntax::span::Span;
+use crate::syntax::token::{Token, TokenType, Trivia};
+
+/// A change to a source: the bytes in `range` replaced by `replacement`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Edit {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+impl Edit {
+    pub fn new(range: Range<usize>, replacement: impl Into<String>) -> Self {
+        Self {
+            range,
+            replacement: replacement.into(),
+        }
+    }
+
+    /// The source with the edit applied.
+    pub fn apply(&self, source: &str) -> String {
+        let mut edited = source.to_string();
+        edited.replace_range(self.range.clone(), &self.replacement);
+        edited
+    }
+}
+
+/// The result of [`Scanner::relex`].
+#[derive(Debug, PartialEq)]
+pub struct Relexed<'a> {
+    /// The items a full scan of the edited source would yield.
+    pub items: Vec<Result<Token<'a>, ScanError>>,
+    /// The indices of `items` that were scanned again, everything else was
+    /// carried over from the previous scan.
+    pub rescanned: Range<usize>,
+}
+
+/// Where the scan of a token begins, which is before its leading trivia.
+pub(crate) fn scan_start(token: &Token) -> Span {
+    token
+        .leading_trivia()
+        .first()
+        .map_or(token.span(), Trivia::span)
+}
+
+/// Whether scanning can restart at `start` when only `source[..limit]` is
+/// known. The items before a restart point may have looked up to two
+/// characters past it, so those must be known too.
+pub(crate) fn lookahead_fits(source: &str, start: usize, limit: usize) -> bool {
+    let mut chars = source[start..].chars();
+    match (chars.next(), chars.next()) {
+        (Some(first), Some(second)) => start + first.len_utf8() + second.len_utf8() <= limit,
+        _ => false,
+    }
+}
+
+/// Tracks how many string interpolations the scan of `previous` was inside
+/// of. Tokens only tell when an interpolation opens or closes if the string
+/// around it was valid, so after an invalid escape the depth is unknown.
+#[derive(Debug, Clone, Copy)]
+struct Depth(Option<usize>);
+
+impl Depth {
+    fn is_outside(&self) -> bool {
+        self.0 == Some(0)
+    }
+
+    fn step(&mut self, item: &Result<Token, ScanError>) {
+        self.0 = match (self.0, item) {
+            (_, Err(ScanError::InvalidEscape { .. })) => None,
+            (Some(depth), Ok(token)) if token.kind() == TokenType::StringStart => Some(depth + 1),
+            (Some(depth), Ok(token)) if token.kind() == TokenType::StringEnd => {
+                Some(depth.saturating_sub(1))
+            }
+            (depth, _) => depth,
+        };
+    }
+}
+
+impl<'a> Scanner<'a, '_> {
+    /// Updates the items of a previous scan after `edit`, re-scanning only
+    /// the part of the source the edit can have changed.
+    ///
+    /// The scanner must be over the edited source and configured like the
+    /// one that produced `previous`. Scanning restarts at the last token
+    /// that is safely before the edit, and stops as soon as it reaches a
+    /// token that was also in `previous` in the same state, after which the
+    /// previous items are reused with their spans moved.
+    ///
+    /// ```
+    /// use interpreter_starter_rust::{Edit, Scanner};
+    ///
+    /// let source = "var a = 1;\nvar b = 2;\nvar c = 3;";
+    /// let previous: Vec<_> = Scanner::from(source).collect();
+    ///
+    /// let edit = Edit::new(19..20, "42");
+    /// let edited = edit.apply(source);
+    /// let relexed = Scanner::from(edited.as_str()).relex(&previous, &edit);
+    ///
+    /// assert_eq!(relexed.items, Scanner::from(edited.as_str()).collect::<Vec<_>>());
+    /// assert!(relexed.rescanned.len() < 4);
+    /// ```
+    pub fn relex(self, previous: &[Result<Token<'_>, ScanError>], edit: &Edit) -> Relexed<'a> {
+        let source = self.source();
+        let edit_end = edit.range.start + edit.replacement.len();
+
+        // Errors are never restart points, in trivia mode the text they
+        // skipped belongs to the next token.
+        let mut restart = None;
+        let mut depth = Depth(Some(0));
+        for (index, item) in previous.iter().enumerate() {
+            if let Ok(token) = item {
+                let start = scan_start(token).start;
+                if start >= edit.range.start {
+                    break;
+                }
+                if index > 0
+                    && depth.is_outside()
+                    && previous[index - 1].is_ok()
+                    && lookahead_fits(source, start, edit.range.start)
+                {
+                    restart = Some(index);
+                }
+            }
+            depth.step(item);
+        }
+
+        let (mut items, mut scanner, mut old) = match restart {
+            Some(index) => {
+                // the text in front of the restart point is unchanged
+                let origin = Span::default().with_source(self.source_id());
+                let kept = previous[..index]
+                    .iter()
+                    .map(|item| relocate(item, origin, origin, source));
+                let at = scan_start(previous[index].as_ref().unwrap());
+                (kept.collect(), self.resume_at(at), index)
+            }
+            None => (vec![], self, 0),
+        };
+        let first_rescanned = items.len();
+
+        let mut old_depth = Depth(Some(0));
+        loop {
+            let outside = scanner.interpolation_depth() == 0;
+            let after_token = items.last().map_or(true, Result::is_ok);
+            let Some(item) = scanner.next() else {
+                break;
+            };
+
+            if let Ok(token) = &item {
+                let at = scan_start(token);
+                if outside && after_token && at.start >= edit_end {
+                    // find the previous token that started at the same text
+                    let target = at.start + edit.range.end - edit_end;
+                    while let Some(previous_item) = previous.get(old) {
+                        match previous_item {
+                            Ok(token) if scan_start(token).start >= target => break,
+                            _ => old_depth.step(previous_item),
+                        }
+                        old += 1;
+                    }
+
+                    if let Some(Ok(previous_token)) = previous.get(old) {
+                        let from = scan_start(previous_token);
+                        if from.start == target
+                            && old_depth.is_outside()
+                            && (old == 0 || previous[old - 1].is_ok())
+                        {
+                            let rescanned = first_rescanned..items.len();
+                            items.extend(
+                                previous[old..]
+                                    .iter()
+                                    .map(|item| relocate(item, from, at, source)),
+                            );
+                            return Relexed { items, rescanned };
+                        }
+                    }
+                }
+            }
+
+            items.push(item);
+        }
+
+        Relexed {
+            rescanned: first_rescanned..items.len(),
+            items,
+        }
+    }
+}
+
+/// Moves an item of the previous scan to the edited source, see
+/// [`Span::relocated`].
+fn relocate<'a>(
+    item: &Result<Token, ScanError>,
+    from: Span,
+    to: Span,
+    source: &'a str,
+) -> Result<Token<'a>, ScanError> {
+    let move_span = |span: Span| span.relocated(from, to);
+    let move_trivia = |trivia: &[Trivia]| -> Vec<Trivia<'a>> {
+        trivia
+            .iter()
+            .map(|trivia| {
+                let span = move_span(trivia.span());
+                Trivia::new(trivia.kind(), &source[span.range()], span)
+            })
+            .collect()
+    };
+
+    match item {
+        Ok(token) => {
+            let span = move_span(token.span());
+            let moved = Token::new(
+                token.kind(),
+                &source[span.range()],
+                token.literal().clone().into_owned(),
+                span,
+            );
+            Ok(moved.with_trivia(
+                move_trivia(token.leading_trivia()),
+                move_trivia(token.trailing_trivia()),
+            ))
+        }
+        Err(error) => {
+            let mut error = error.clone();
+            let span = error.span_mut();
+            *span = move_span(*span);
+            Err(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A xorshift generator, enough to pick test cases reproducibly.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    const PIECES: &[&str] = &[
+        "var", "x", "é", "fun", "1", "2.5", "0x1f", "1_", "1e", ".", "\"s\"", "\"a${", "}\"", "}",
+        "{", "(", ")", "+", "-", "=", "==", "!", "/", "*", "//c\n", "/*", "*/", " ", " ", "\n",
+        "\t", "\\q", "\\n", "@", "ü", "$", ";", "\"", "${",
+    ];
+
+    fn random_text(rng: &mut Rng, max_pieces: usize) -> String {
+        (0..rng.below(max_pieces + 1))
+            .map(|_| PIECES[rng.below(PIECES.len())])
+            .collect()
+    }
+
+    fn char_boundary(source: &str, mut offset: usize) -> usize {
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+
+    fn full_scan(source: &str, trivia: bool) -> Vec<Result<Token<'_>, ScanError>> {
+        let scanner = Scanner::from(source);
+        match trivia {
+            true => scanner.with_trivia().collect(),
+            false => scanner.collect(),
+        }
+    }
+
+    #[test]
+    fn test_relex_matches_full_scan() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+        for case in 0..3000 {
+            let source = random_text(&mut rng, 40);
+            let a = char_boundary(&source, rng.below(source.len() + 1));
+            let b = char_boundary(&source, rng.below(source.len() + 1));
+            let edit = Edit::new(a.min(b)..a.max(b), random_text(&mut rng, 3));
+            let edited = edit.apply(&source);
+
+            for trivia in [false, true] {
+                let previous = full_scan(&source, trivia);
+                let scanner = Scanner::from(edited.as_str());
+                let scanner = if trivia {
+                    scanner.with_trivia()
+                } else {
+                    scanner
+                };
+
+                assert_eq!(
+                    scanner.relex(&previous, &edit).items,
+                    full_scan(&edited, trivia),
+                    "case {case}: {source:?} with {edit:?}, trivia: {trivia}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_relex_rescans_locally() {
+        let line = "var total = \"sum: ${a + b}\"; // note\n";
+        let source = line.repeat(200);
+        let previous: Vec<_> = Scanner::from(source.as_str()).with_trivia().collect();
+
+        // rename `b` on line 101
+        let offset = line.len() * 100 + line.find('b').unwrap();
+        let edit = Edit::new(offset..offset + 1, "count");
+        let edited = edit.apply(&source);
+        let relexed = Scanner::from(edited.as_str())
+            .with_trivia()
+            .relex(&previous, &edit);
+
+        assert_eq!(
+            relexed.items,
+            Scanner::from(edited.as_str())
+                .with_trivia()
+                .collect::<Vec<_>>()
+        );
+        assert!(relexed.rescanned.len() <= 10, "{:?}", relexed.rescanned);
+        assert!(relexed.rescanned.start > 800);
+    }
+
+    #[test]
+    fn test_relex_after_invalid_escape() {
+        // the depth after `"\q${` is unknown, so scanning does not resync
+        // inside what might be an interpolation
+        let source = "\"\\q${ a \" } \" b";
+        let previous: Vec<_> = Scanner::from(source).collect();
+        let edit = Edit::new(6..7, "c");
+        let edited = edit.apply(source);
+
+        let relexed = Scanner::from(edited.as_str()).relex(&previous, &edit);
+        assert_eq!(
+            relexed.items,
+            Scanner::from(edited.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(relexed.rescanned.start, 0);
+    }
+}
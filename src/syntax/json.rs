@@ -0,0 +1,163 @@
+//! JSON encoding of scanner output, for tools that would rather not parse the
+//! `TYPE lexeme literal` text format.
+
+use std::fmt::Write;
+
+use crate::syntax::error::ScanError;
+use crate::syntax::span::Span;
+use crate::syntax::token::{Literal, Token};
+
+impl Token<'_> {
+    /// The token as a single line JSON object with its kind, lexeme, literal
+    /// value, position and byte span.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"kind\":");
+        write_string(&mut json, &self.kind().to_string());
+        json.push_str(",\"lexeme\":");
+        write_string(&mut json, self.lexeme());
+        json.push_str(",\"literal\":");
+        match self.literal() {
+            Literal::String(s) => write_string(&mut json, s),
+            Literal::Integer(n) => write!(json, "{n}").unwrap(),
+            Literal::Float(n) if n.is_finite() => write!(json, "{n:?}").unwrap(),
+            // JSON has no infinity
+            Literal::Float(_) | Literal::None => json.push_str("null"),
+        }
+        write_location(&mut json, self.span());
+        json.push('}');
+        json
+    }
+}
+
+impl ScanError {
+    /// The error as a single line JSON object with its code, message,
+    /// position and byte span.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"code\":");
+        write_string(&mut json, self.code());
+        json.push_str(",\"message\":");
+        write_string(&mut json, &self.to_string());
+        write_location(&mut json, self.span());
+        json.push('}');
+        json
+    }
+}
+
+/// The items of a scan of the file `name` as a single line JSON document,
+/// `{"file":..,"tokens":[..],"diagnostics":[..]}`.
+pub fn file_record<'a, 't: 'a>(
+    name: &str,
+    items: impl IntoIterator<Item = &'a Result<Token<'t>, ScanError>>,
+) -> String {
+    let mut tokens = vec![];
+    let mut diagnostics = vec![];
+    for item in items {
+        match item {
+            Ok(token) => tokens.push(token.to_json()),
+            Err(error) => diagnostics.push(error.to_json()),
+        }
+    }
+
+    let mut json = String::from("{\"file\":");
+    write_string(&mut json, name);
+    write!(
+        json,
+        ",\"tokens\":[{}],\"diagnostics\":[{}]}}",
+        tokens.join(","),
+        diagnostics.join(",")
+    )
+    .unwrap();
+    json
+}
+
+/// One item of a scan of the file `name` as a JSON object for a JSON Lines
+/// stream, `{"file":..,"token":{..}}` or `{"file":..,"diagnostic":{..}}`.
+pub fn file_line(name: &str, item: &Result<Token, ScanError>) -> String {
+    let mut json = String::from("{\"file\":");
+    write_string(&mut json, name);
+    match item {
+        Ok(token) => write!(json, ",\"token\":{}}}", token.to_json()),
+        Err(error) => write!(json, ",\"diagnostic\":{}}}", error.to_json()),
+    }
+    .unwrap();
+    json
+}
+
+fn write_location(json: &mut String, span: Span) {
+    write!(
+        json,
+        ",\"line\":{},\"column\":{},\"span\":{{\"start\":{},\"end\":{}}}",
+        span.start_pos.line, span.start_pos.column, span.start, span.end
+    )
+    .unwrap();
+}
+
+/// Appends `s` as a quoted JSON string.
+pub(crate) fn write_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c < ' ' => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::scanner::Scanner;
+
+    #[test]
+    fn test_token_json() {
+        let (tokens, _) = Scanner::from("x = \"a\\\"b\\n\" + 1 * 2.5").scan_tokens();
+        let json: Vec<String> = tokens.iter().map(|token| token.to_json()).collect();
+
+        assert_eq!(
+            json,
+            vec![
+                r#"{"kind":"IDENTIFIER","lexeme":"x","literal":null,"line":1,"column":1,"span":{"start":0,"end":1}}"#,
+                r#"{"kind":"EQUAL","lexeme":"=","literal":null,"line":1,"column":3,"span":{"start":2,"end":3}}"#,
+                r#"{"kind":"STRING","lexeme":"\"a\\\"b\\n\"","literal":"a\"b\n","line":1,"column":5,"span":{"start":4,"end":12}}"#,
+                r#"{"kind":"PLUS","lexeme":"+","literal":null,"line":1,"column":14,"span":{"start":13,"end":14}}"#,
+                r#"{"kind":"NUMBER","lexeme":"1","literal":1,"line":1,"column":16,"span":{"start":15,"end":16}}"#,
+                r#"{"kind":"STAR","lexeme":"*","literal":null,"line":1,"column":18,"span":{"start":17,"end":18}}"#,
+                r#"{"kind":"NUMBER","lexeme":"2.5","literal":2.5,"line":1,"column":20,"span":{"start":19,"end":22}}"#,
+                r#"{"kind":"EOF","lexeme":"","literal":null,"line":1,"column":23,"span":{"start":22,"end":22}}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_json() {
+        let (_, diagnostics) = Scanner::from("\n  \u{7}").scan_tokens();
+
+        assert_eq!(
+            diagnostics[0].to_json(),
+            r#"{"code":"L0002","message":"Unexpected character: \u0007","line":2,"column":3,"span":{"start":3,"end":4}}"#
+        );
+    }
+
+    #[test]
+    fn test_file_json() {
+        let items: Vec<_> = Scanner::from("@").collect();
+
+        assert_eq!(
+            file_record("a \"b\".lox", &items),
+            concat!(
+                r#"{"file":"a \"b\".lox","tokens":[{"kind":"EOF","lexeme":"","literal":null,"line":1,"column":2,"span":{"start":1,"end":1}}],"#,
+                r#""diagnostics":[{"code":"L0002","message":"Unexpected character: @","line":1,"column":1,"span":{"start":0,"end":1}}]}"#
+            )
+        );
+        assert_eq!(
+            file_line("-", &items[1]),
+            r#"{"file":"-","token":{"kind":"EOF","lexeme":"","literal":null,"line":1,"column":2,"span":{"start":1,"end":1}}}"#
+        );
+    }
+}
@@ -1,2 +1,12 @@
+pub mod chunked;
+pub mod diagnostic;
+pub mod dialect;
+pub mod error;
+pub mod explain;
+pub mod incremental;
+pub mod json;
 pub mod scanner;
-mod token;
+pub mod source;
+pub mod span;
+pub mod token;
+mod unicode;
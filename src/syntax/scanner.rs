@@ -1,58 +1,147 @@
-use std::iter::Peekable;
-use std::str::Chars;
-use std::sync::OnceLock;
-use std::{collections::HashMap, num::NonZeroUsize};
-
-use crate::syntax::token::{Literal, Token, TokenType};
-
-static RESERVED_KEYWORDS: OnceLock<HashMap<&'static str, TokenType>> = OnceLock::new();
+use std::borrow::Cow;
+use std::iter::FusedIterator;
+use std::num::NonZeroUsize;
+
+use crate::syntax::dialect::Dialect;
+use crate::syntax::error::ScanError;
+use crate::syntax::source::SourceId;
+use crate::syntax::span::{Position, Span};
+use crate::syntax::token::{Literal, Token, TokenType, Trivia, TriviaKind};
+use crate::syntax::unicode;
+
+/// A `${` inside a string literal whose closing `}` has not been seen yet.
+#[derive(Debug)]
+struct Interpolation {
+    open: Span,   // the `${`
+    depth: usize, // unmatched `{` inside the interpolated expression
+}
 
 #[derive(Debug)]
-pub struct Scanner<'a> {
+pub struct Scanner<'a, 'd> {
     source: &'a str,
-    c_iter: Peekable<Chars<'a>>,
-    tokens: Vec<Token>,
     start: usize,   // start offset
     current: usize, // current offset
+    start_pos: Position,
     line: NonZeroUsize,
-    pub has_error: bool,
+    column: NonZeroUsize,
+    scanned_preamble: bool,
+    emitted_eof: bool,
+    keep_trivia: bool,
+    trivia: Vec<Trivia<'a>>, // trivia not yet attached to a token
+    interpolations: Vec<Interpolation>,
+    dialect: &'d Dialect,
+    source_id: SourceId,
 }
 
-impl<'a> Scanner<'a> {
+impl<'a> Scanner<'a, 'static> {
     fn new(buffer: &'a str) -> Self {
         Self {
             source: buffer,
-            c_iter: buffer.chars().peekable(),
-            tokens: vec![],
             start: 0,
             current: 0,
-            line: unsafe { NonZeroUsize::new_unchecked(1) },
-            has_error: false,
+            start_pos: Position::default(),
+            line: NonZeroUsize::MIN,
+            column: NonZeroUsize::MIN,
+            scanned_preamble: false,
+            emitted_eof: false,
+            keep_trivia: false,
+            trivia: vec![],
+            interpolations: vec![],
+            dialect: Dialect::lox_ref(),
+            source_id: SourceId::default(),
         }
     }
+}
+
+impl<'a, 'd> Scanner<'a, 'd> {
+    /// Scans with the keywords and tokens of `dialect` instead of plain Lox.
+    /// The tokens only borrow the source, so they may outlive `dialect`.
+    pub fn with_dialect<'e>(self, dialect: &'e Dialect) -> Scanner<'a, 'e> {
+        Scanner { dialect, ..self }
+    }
+
+    /// Tags every span with `source_id`, the id of the file being scanned in
+    /// a [`SourceMap`](crate::SourceMap).
+    pub fn with_source(mut self, source_id: SourceId) -> Self {
+        self.source_id = source_id;
+        self
+    }
+
+    /// Keeps whitespace, newlines and comments as trivia attached to the
+    /// tokens, so that concatenating every token's leading trivia, lexeme
+    /// and trailing trivia reproduces the source exactly. Text that was
+    /// reported as an error is kept as `TriviaKind::Skipped`.
+    pub fn with_trivia(mut self) -> Self {
+        self.keep_trivia = true;
+        self
+    }
+
+    /// Continues scanning at `at.start`, which must be where the scan of a
+    /// token begins (including its leading trivia) outside of any string
+    /// interpolation. Used to re-scan only part of an edited source.
+    pub(crate) fn resume_at(mut self, at: Span) -> Self {
+        self.start = at.start;
+        self.current = at.start;
+        self.start_pos = at.start_pos;
+        self.line = at.start_pos.line;
+        self.column = at.start_pos.column;
+        self.scanned_preamble = true;
+        self
+    }
+
+    pub(crate) fn source(&self) -> &'a str {
+        self.source
+    }
+
+    pub(crate) fn source_id(&self) -> SourceId {
+        self.source_id
+    }
+
+    /// The number of string interpolations the scanner is inside of.
+    pub(crate) fn interpolation_depth(&self) -> usize {
+        self.interpolations.len()
+    }
 
     fn advance(&mut self) -> Option<char> {
-        self.c_iter.next().and_then(|v| {
-            self.current += v.len_utf8();
-            Some(v)
-        })
+        let c = self.peek()?;
+        self.current += c.len_utf8();
+
+        if c == '\n' {
+            self.increase_line();
+        } else {
+            self.column = self.column.saturating_add(1);
+        }
+
+        Some(c)
     }
 
     fn advance_if<F: Fn(char) -> bool>(&mut self, predicate: F) -> Option<char> {
-        let Some(c) = self.peek() else {
-            return None;
-        };
+        let c = self.peek()?;
 
-        if !predicate(*c) {
+        if !predicate(c) {
             return None;
         }
 
         self.advance()
     }
 
+    /// Consumes the next byte if `predicate` holds for it, without decoding
+    /// it. The predicate must only hold for ASCII bytes other than `\n`.
+    fn advance_byte_if<F: Fn(u8) -> bool>(&mut self, predicate: F) -> Option<u8> {
+        let b = *self.source.as_bytes().get(self.current)?;
+
+        if !predicate(b) {
+            return None;
+        }
+
+        self.current += 1;
+        self.column = self.column.saturating_add(1);
+        Some(b)
+    }
+
     fn advance_while<F: Fn(char) -> bool>(&mut self, predicate: F) {
         while let Some(c) = self.peek() {
-            if !predicate(*c) {
+            if !predicate(c) {
                 break;
             }
 
@@ -60,193 +149,602 @@ impl<'a> Scanner<'a> {
         }
     }
 
-    fn peek(&mut self) -> Option<&char> {
-        self.c_iter.peek()
+    /// Consumes bytes while `predicate` holds, without decoding them. The
+    /// predicate must not hold for `\n`, and must hold either for every
+    /// non-ASCII byte or for none, so that a run never ends inside of a
+    /// multi-byte character.
+    fn advance_bytes_while<F: Fn(u8) -> bool>(&mut self, predicate: F) {
+        let mut chars = 0;
+        for &b in &self.source.as_bytes()[self.current..] {
+            if !predicate(b) {
+                break;
+            }
+
+            self.current += 1;
+            // continuation bytes do not start a character
+            chars += usize::from(b & 0xC0 != 0x80);
+        }
+
+        self.column = self.column.saturating_add(chars);
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.char_at(self.current)
+    }
+
+    fn peek_next(&self) -> Option<char> {
+        let c = self.peek()?;
+        self.char_at(self.current + c.len_utf8())
     }
 
-    fn peek_next(&mut self) -> Option<char> {
-        // INVESTIGATE better way to do this?
-        let mut iter = self.c_iter.clone();
-        iter.next();
-        // return next again instead of peek since peek returns a ref to the underlying value
-        // this breaks borrowing rules since the valeus of the cloned iter does not live past this
-        // function scope
-        iter.next()
+    /// The character starting at byte `offset`. Only non-ASCII characters
+    /// need decoding.
+    fn char_at(&self, offset: usize) -> Option<char> {
+        match *self.source.as_bytes().get(offset)? {
+            b if b.is_ascii() => Some(char::from(b)),
+            _ => self.source[offset..].chars().next(),
+        }
     }
 
-    fn add_token_without_literal(&mut self, token_type: TokenType) {
-        self.add_token(token_type, Literal::None)
+    fn token_without_literal(&self, token_type: TokenType) -> Option<Result<Token<'a>, ScanError>> {
+        self.token(token_type, Literal::None)
     }
 
     fn lexeme(&self) -> &'a str {
         &self.source[self.start..self.current]
     }
 
-    fn add_token(&mut self, token_type: TokenType, literal: Literal) {
-        let token = Token::new(token_type, self.lexeme().to_string(), literal, self.line);
-        self.tokens.push(token);
+    fn current_pos(&self) -> Position {
+        Position::new(self.line, self.column)
+    }
+
+    fn span(&self) -> Span {
+        Span::new(self.start, self.current, self.start_pos, self.current_pos())
+            .with_source(self.source_id)
+    }
+
+    fn token(
+        &self,
+        token_type: TokenType,
+        literal: Literal<'a>,
+    ) -> Option<Result<Token<'a>, ScanError>> {
+        if self.dialect.is_disabled(token_type) {
+            return Some(Err(ScanError::DisabledToken {
+                lexeme: self.lexeme().to_string(),
+                span: self.span(),
+            }));
+        }
+
+        let token = Token::new(token_type, self.lexeme(), literal, self.span());
+        Some(Ok(token))
+    }
+
+    fn trivia(&mut self, kind: TriviaKind) -> Option<Result<Token<'a>, ScanError>> {
+        if self.keep_trivia {
+            self.trivia
+                .push(Trivia::new(kind, self.lexeme(), self.span()));
+        }
+
+        None
+    }
+
+    fn attach_trivia(&mut self, token: Token<'a>) -> Token<'a> {
+        let leading = std::mem::take(&mut self.trivia);
+
+        // trailing trivia stops in front of the newline, which becomes
+        // leading trivia of the next token. Block comments may span lines
+        // and always lead the next token.
+        while matches!(self.peek(), Some(' ' | '\r' | '\t'))
+            || (self.peek() == Some('/') && self.peek_next() == Some('/'))
+        {
+            self.start = self.current;
+            self.start_pos = self.current_pos();
+            self.scan_token();
+        }
+
+        let trailing = std::mem::take(&mut self.trivia);
+        token.with_trivia(leading, trailing)
+    }
+
+    /// Skips a UTF-8 byte order mark and a `#!` interpreter line at the very
+    /// start of the source, keeping them as trivia.
+    fn scan_preamble(&mut self) {
+        self.scanned_preamble = true;
+
+        if self.advance_if(|c| c == '\u{FEFF}').is_some() {
+            // the mark is invisible, so it does not take up a column
+            self.column = NonZeroUsize::MIN;
+            self.trivia(TriviaKind::Bom);
+            self.start = self.current;
+            self.start_pos = self.current_pos();
+        }
+
+        if self.peek() == Some('#') && self.peek_next() == Some('!') {
+            self.advance_bytes_while(|b| b != b'\n');
+            self.trivia(TriviaKind::Shebang);
+        }
     }
 
     fn increase_line(&mut self) {
-        unsafe { self.line = NonZeroUsize::new_unchecked(self.line.get() + 1) }
+        self.line = self.line.saturating_add(1);
+        self.column = NonZeroUsize::MIN;
     }
 
-    fn add_string(&mut self) {
-        while let Some(c) = self.peek() {
-            if *c == '"' {
-                break;
+    /// Scans a string literal, or the part of one following an interpolated
+    /// expression when `continued` is set. A `${` ends the current part and
+    /// the expression tokens are scanned as usual until the matching `}`.
+    fn scan_string(&mut self, continued: bool) -> Option<Result<Token<'a>, ScanError>> {
+        // only allocated once an escape sequence makes the value differ
+        // from the text between the delimiters
+        let mut value: Option<String> = None;
+        let mut error = None;
+        let mut interpolated = false;
+
+        loop {
+            // text without escapes or delimiters is taken over as is
+            let run_start = self.current;
+            self.advance_bytes_while(|b| !matches!(b, b'"' | b'\\' | b'$' | b'\n'));
+            if let Some(value) = value.as_mut() {
+                value.push_str(&self.source[run_start..self.current]);
             }
 
-            if *c == '\n' {
-                self.increase_line();
+            let Some(c) = self.peek() else {
+                break;
+            };
+            match c {
+                '"' => break,
+                '$' if self.peek_next() == Some('{') => {
+                    interpolated = true;
+                    break;
+                }
+                '\\' => {
+                    let escape_start = self.current;
+                    let escape_pos = self.current_pos();
+                    self.advance();
+
+                    match self.scan_escape() {
+                        Some(c) => value
+                            // both `"` and `}` are a single byte
+                            .get_or_insert_with(|| {
+                                self.source[self.start + 1..escape_start].to_string()
+                            })
+                            .push(c),
+                        None => {
+                            let span = Span::new(
+                                escape_start,
+                                self.current,
+                                escape_pos,
+                                self.current_pos(),
+                            )
+                            .with_source(self.source_id);
+                            error.get_or_insert(ScanError::InvalidEscape {
+                                sequence: self.source[span.range()].to_string(),
+                                span,
+                            });
+                        }
+                    }
+                }
+                c => {
+                    self.advance();
+                    if let Some(value) = value.as_mut() {
+                        value.push(c);
+                    }
+                }
             }
-
-            self.advance();
         }
 
         if self.peek().is_none() {
-            self.has_error = true;
-            eprintln!("[line {}] Error: Unterminated string.", self.line.get(),);
-            return;
+            return Some(Err(ScanError::UnterminatedString { span: self.span() }));
         }
 
+        let end = self.current;
+        let end_pos = self.current_pos();
         self.advance();
 
-        let s =
-            self.lexeme()[self.start + '"'.len_utf8()..self.current - '"'.len_utf8()].to_string();
-        self.add_token(TokenType::String, Literal::String(s));
+        if interpolated {
+            self.advance(); // consume '{'
+            let open = Span::new(end, self.current, end_pos, self.current_pos())
+                .with_source(self.source_id);
+            self.interpolations.push(Interpolation { open, depth: 0 });
+        }
+
+        if let Some(error) = error {
+            return Some(Err(error));
+        }
+
+        let literal = match value {
+            Some(value) => Cow::Owned(value),
+            None => Cow::Borrowed(&self.source[self.start + 1..end]),
+        };
+        let token_type = match (continued, interpolated) {
+            (false, false) => TokenType::String,
+            (false, true) => TokenType::StringStart,
+            (true, true) => TokenType::StringMiddle,
+            (true, false) => TokenType::StringEnd,
+        };
+        self.token(token_type, Literal::String(literal))
+    }
+
+    /// Decodes the escape sequence following a backslash. Returns `None` if
+    /// it is not a valid escape, having consumed as much of it as belongs
+    /// to the sequence.
+    fn scan_escape(&mut self) -> Option<char> {
+        match self.advance()? {
+            '"' => Some('"'),
+            '\\' => Some('\\'),
+            '$' => Some('$'),
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            'u' => {
+                self.advance_if(|c| c == '{')?;
+                let digits_start = self.current;
+                self.advance_bytes_while(|b| b.is_ascii_hexdigit());
+                let digits = &self.source[digits_start..self.current];
+                self.advance_if(|c| c == '}')?;
+
+                if digits.is_empty() || digits.len() > 6 {
+                    return None;
+                }
+
+                u32::from_str_radix(digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            }
+            _ => None,
+        }
+    }
+
+    fn scan_block_comment(&mut self) -> Option<Result<Token<'a>, ScanError>> {
+        let mut depth = 1;
+
+        while depth > 0 {
+            self.advance_bytes_while(|b| !matches!(b, b'/' | b'*' | b'\n'));
+            match self.advance() {
+                Some('/') if self.advance_if(|c| c == '*').is_some() => depth += 1,
+                Some('*') if self.advance_if(|c| c == '/').is_some() => depth -= 1,
+                Some(_) => {}
+                None => {
+                    // point at the opening `/*` rather than the end of input
+                    let end_pos =
+                        Position::new(self.start_pos.line, self.start_pos.column.saturating_add(2));
+                    let span = Span::new(self.start, self.start + 2, self.start_pos, end_pos)
+                        .with_source(self.source_id);
+                    return Some(Err(ScanError::UnterminatedBlockComment { span }));
+                }
+            }
+        }
+
+        self.trivia(TriviaKind::BlockComment)
     }
 
-    fn add_number(&mut self) {
-        self.advance_while(|c| c.is_ascii_digit());
+    /// Scans a number literal whose first digit has already been consumed.
+    ///
+    /// Besides `digits[.digits]` this accepts an exponent (`6.02e23`), `_`
+    /// digit separators (`1_000`) and `0x`, `0o` and `0b` prefixed integers.
+    /// A malformed literal is consumed completely before being reported.
+    fn scan_number(&mut self, first: char) -> Option<Result<Token<'a>, ScanError>> {
+        let radix = match (first, self.peek()) {
+            ('0', Some('x' | 'X')) => Some(16),
+            ('0', Some('o' | 'O')) => Some(8),
+            ('0', Some('b' | 'B')) => Some(2),
+            _ => None,
+        };
+
+        if let Some(radix) = radix {
+            self.advance(); // consume the prefix letter
+            return self.scan_radix_number(radix);
+        }
+
+        let mut result = self.scan_digits(self.start, "missing digits");
+        let mut is_float = false;
 
-        if self.peek() == Some(&'.') && self.peek_next().map_or(false, |c| c.is_ascii_digit()) {
+        if self.peek() == Some('.') && self.peek_next().is_some_and(|c| c.is_ascii_digit()) {
             self.advance(); // consume '.'
-            self.advance_while(|c| c.is_ascii_digit());
+            result = result.and(self.scan_digits(self.current, "missing digits"));
+            is_float = true;
+        }
+
+        // an `e` followed by a letter starts an identifier, as in `1else`
+        let exponent = match (self.peek(), self.peek_next()) {
+            (Some('e' | 'E'), Some(c)) if c.is_ascii_digit() => true,
+            (Some('e' | 'E'), Some('+' | '-')) => true,
+            (Some('e' | 'E'), next) => !next.is_some_and(unicode::is_xid_continue),
+            _ => false,
+        };
+        if exponent {
+            self.advance(); // consume 'e'
+            self.advance_if(|c| c == '+' || c == '-');
+            result = result.and(self.scan_digits(self.current, "missing exponent digits"));
+            is_float = true;
+        }
+
+        if let Err(reason) = result {
+            return self.malformed_number(reason);
+        }
+
+        let digits = self.lexeme().replace('_', "");
+        if is_float {
+            return match digits.parse::<f64>() {
+                Ok(num) => self.token(TokenType::Number, Literal::Float(num)),
+                Err(_) => self.malformed_number("not a valid number"),
+            };
+        }
+
+        match digits.parse::<i64>() {
+            Ok(num) => self.token(TokenType::Number, Literal::Integer(num)),
+            Err(_) => Some(Err(ScanError::IntegerOverflow { span: self.span() })),
+        }
+    }
+
+    /// Consumes decimal digits and separators, checking the group that
+    /// started at `start`.
+    fn scan_digits(&mut self, start: usize, missing: &'static str) -> Result<(), &'static str> {
+        self.advance_bytes_while(|b| b.is_ascii_digit() || b == b'_');
+
+        let digits = &self.source[start..self.current];
+        if !digits.bytes().any(|b| b.is_ascii_digit()) {
+            Err(missing)
+        } else if digits.ends_with('_') {
+            Err("trailing underscore")
+        } else {
+            Ok(())
+        }
+    }
+
+    fn scan_radix_number(&mut self, radix: u32) -> Option<Result<Token<'a>, ScanError>> {
+        let digits_start = self.current;
+        // consume letters too, so `0xfg` is one malformed literal rather
+        // than a number followed by an identifier
+        self.advance_bytes_while(|b| b.is_ascii_alphanumeric() || b == b'_');
+        let digits = &self.source[digits_start..self.current];
+
+        if !digits.chars().all(|c| c.is_digit(radix) || c == '_') {
+            return self.malformed_number(match radix {
+                16 => "invalid hexadecimal digit",
+                8 => "invalid octal digit",
+                _ => "invalid binary digit",
+            });
+        }
+        if !digits.chars().any(|c| c.is_digit(radix)) {
+            return self.malformed_number("missing digits after base prefix");
+        }
+        if digits.ends_with('_') {
+            return self.malformed_number("trailing underscore");
+        }
+
+        let num = digits
+            .chars()
+            .filter_map(|c| c.to_digit(radix))
+            .try_fold(0i64, |num, digit| {
+                num.checked_mul(radix as i64)?.checked_add(digit as i64)
+            });
+
+        match num {
+            Some(num) => self.token(TokenType::Number, Literal::Integer(num)),
+            None => Some(Err(ScanError::IntegerOverflow { span: self.span() })),
         }
+    }
 
-        let num = self
-            .lexeme()
-            .parse::<f64>()
-            .expect("Should be a valid number");
-        self.add_token(TokenType::Number, Literal::Number(num));
+    fn malformed_number(&self, reason: &'static str) -> Option<Result<Token<'a>, ScanError>> {
+        Some(Err(ScanError::MalformedNumber {
+            reason,
+            span: self.span(),
+        }))
     }
 
-    fn add_identifier(&mut self, c: char) {
-        self.advance_while(|ch| ch.is_alphanumeric() || ch == '_');
+    /// Scans an identifier following UAX #31: an XID_Start character or `_`
+    /// followed by XID_Continue characters.
+    fn scan_identifier(&mut self) -> Option<Result<Token<'a>, ScanError>> {
+        self.advance_bytes_while(|b| b.is_ascii_alphanumeric() || b == b'_');
+        if self
+            .source
+            .as_bytes()
+            .get(self.current)
+            .is_some_and(|b| !b.is_ascii())
+        {
+            self.advance_while(unicode::is_xid_continue);
+        }
         let lexeme = self.lexeme();
 
-        let x = RESERVED_KEYWORDS.get_or_init(|| {
-            let mut rkw = HashMap::new();
-            rkw.insert("and", TokenType::And);
-            rkw.insert("class", TokenType::Class);
-            rkw.insert("else", TokenType::Else);
-            rkw.insert("false", TokenType::False);
-            rkw.insert("for", TokenType::For);
-            rkw.insert("fun", TokenType::Fun);
-            rkw.insert("if", TokenType::If);
-            rkw.insert("nil", TokenType::Nil);
-            rkw.insert("or", TokenType::Or);
-            rkw.insert("print", TokenType::Print);
-            rkw.insert("return", TokenType::Return);
-            rkw.insert("super", TokenType::Super);
-            rkw.insert("this", TokenType::This);
-            rkw.insert("true", TokenType::True);
-            rkw.insert("var", TokenType::Var);
-            rkw.insert("while", TokenType::While);
-            rkw
-        });
-
-        match x.get(lexeme) {
-            Some(tt) => self.add_token_without_literal(*tt),
-            _ => self.add_token_without_literal(TokenType::Identifier),
-        };
+        if !lexeme.is_ascii()
+            && !unicode::is_single_script(lexeme)
+            && unicode::is_confusable(lexeme)
+        {
+            return Some(Err(ScanError::ConfusableIdentifier {
+                identifier: lexeme.to_string(),
+                span: self.span(),
+            }));
+        }
+
+        match self.dialect.keyword(lexeme) {
+            Some(tt) => self.token_without_literal(tt),
+            _ => self.token_without_literal(TokenType::Identifier),
+        }
     }
 
-    pub fn scan_token(&mut self) {
-        let c = self.advance();
-        let Some(c) = c else {
-            self.add_token_without_literal(TokenType::Eof);
-            return;
+    /// Scans a single lexeme starting at `self.start`. Returns `None` when
+    /// the lexeme does not produce a token, like whitespace and comments.
+    fn scan_token(&mut self) -> Option<Result<Token<'a>, ScanError>> {
+        // only non-ASCII characters and newlines need more than a byte
+        let c = match self.advance_byte_if(|b| b.is_ascii() && b != b'\n') {
+            Some(b) => char::from(b),
+            None => self.advance()?,
         };
 
         match c {
             // single-character tokens
-            '(' => self.add_token_without_literal(TokenType::LeftParen),
-            ')' => self.add_token_without_literal(TokenType::RightParen),
-            '{' => self.add_token_without_literal(TokenType::LeftBrace),
-            '}' => self.add_token_without_literal(TokenType::RightBrace),
-            '.' => self.add_token_without_literal(TokenType::Dot),
-            '*' => self.add_token_without_literal(TokenType::Star),
-            '+' => self.add_token_without_literal(TokenType::Plus),
-            '-' => self.add_token_without_literal(TokenType::Minus),
-            ';' => self.add_token_without_literal(TokenType::Semicolon),
-            ',' => self.add_token_without_literal(TokenType::Comma),
+            '(' => self.token_without_literal(TokenType::LeftParen),
+            ')' => self.token_without_literal(TokenType::RightParen),
+            '{' => {
+                if let Some(interpolation) = self.interpolations.last_mut() {
+                    interpolation.depth += 1;
+                }
+                self.token_without_literal(TokenType::LeftBrace)
+            }
+            '}' => match self.interpolations.last_mut() {
+                Some(interpolation) if interpolation.depth == 0 => {
+                    self.interpolations.pop();
+                    self.scan_string(true)
+                }
+                Some(interpolation) => {
+                    interpolation.depth -= 1;
+                    self.token_without_literal(TokenType::RightBrace)
+                }
+                None => self.token_without_literal(TokenType::RightBrace),
+            },
+            '.' => self.token_without_literal(TokenType::Dot),
+            ';' => self.token_without_literal(TokenType::Semicolon),
+            ',' => self.token_without_literal(TokenType::Comma),
+            '%' => self.token_without_literal(TokenType::Percent),
+            '[' => self.token_without_literal(TokenType::LeftBracket),
+            ']' => self.token_without_literal(TokenType::RightBracket),
+            ':' => self.token_without_literal(TokenType::Colon),
+            '?' => self.token_without_literal(TokenType::Question),
 
             // single-or-double character tokens
-            '!' => match self.advance_if(|c| c == '=') {
-                Some(_) => self.add_token_without_literal(TokenType::BangEqual),
-                _ => self.add_token_without_literal(TokenType::Bang),
+            '*' => match self.advance_byte_if(|b| b == b'*' || b == b'=') {
+                Some(b'*') => self.token_without_literal(TokenType::StarStar),
+                Some(_) => self.token_without_literal(TokenType::StarEqual),
+                _ => self.token_without_literal(TokenType::Star),
+            },
+            '+' => match self.advance_byte_if(|b| b == b'+' || b == b'=') {
+                Some(b'+') => self.token_without_literal(TokenType::PlusPlus),
+                Some(_) => self.token_without_literal(TokenType::PlusEqual),
+                _ => self.token_without_literal(TokenType::Plus),
+            },
+            '-' => match self.advance_byte_if(|b| b == b'-' || b == b'=' || b == b'>') {
+                Some(b'-') => self.token_without_literal(TokenType::MinusMinus),
+                Some(b'>') => self.token_without_literal(TokenType::Arrow),
+                Some(_) => self.token_without_literal(TokenType::MinusEqual),
+                _ => self.token_without_literal(TokenType::Minus),
+            },
+            '!' => match self.advance_byte_if(|b| b == b'=') {
+                Some(_) => self.token_without_literal(TokenType::BangEqual),
+                _ => self.token_without_literal(TokenType::Bang),
             },
-            '=' => match self.advance_if(|c| c == '=') {
-                Some(_) => self.add_token_without_literal(TokenType::EqualEqual),
-                _ => self.add_token_without_literal(TokenType::Equal),
+            '=' => match self.advance_byte_if(|b| b == b'=' || b == b'>') {
+                Some(b'>') => self.token_without_literal(TokenType::FatArrow),
+                Some(_) => self.token_without_literal(TokenType::EqualEqual),
+                _ => self.token_without_literal(TokenType::Equal),
             },
-            '>' => match self.advance_if(|c| c == '=') {
-                Some(_) => self.add_token_without_literal(TokenType::GreaterEqual),
-                _ => self.add_token_without_literal(TokenType::Greater),
+            '>' => match self.advance_byte_if(|b| b == b'=') {
+                Some(_) => self.token_without_literal(TokenType::GreaterEqual),
+                _ => self.token_without_literal(TokenType::Greater),
             },
-            '<' => match self.advance_if(|c| c == '=') {
-                Some(_) => self.add_token_without_literal(TokenType::LessEqual),
-                _ => self.add_token_without_literal(TokenType::Less),
+            '<' => match self.advance_byte_if(|b| b == b'=') {
+                Some(_) => self.token_without_literal(TokenType::LessEqual),
+                _ => self.token_without_literal(TokenType::Less),
             },
-            '/' => match self.advance_if(|c| c == '/') {
+            '/' => match self.advance_byte_if(|b| b == b'/' || b == b'*' || b == b'=') {
                 // We do not create a token for comments
-                Some(_) => self.advance_while(|c| c != '\n'),
-                _ => self.add_token_without_literal(TokenType::Slash),
+                Some(b'/') => {
+                    self.advance_bytes_while(|b| b != b'\n');
+                    self.trivia(TriviaKind::LineComment)
+                }
+                Some(b'*') => self.scan_block_comment(),
+                Some(_) => self.token_without_literal(TokenType::SlashEqual),
+                _ => self.token_without_literal(TokenType::Slash),
             },
 
-            // ignore whitespace
-            ' ' | '\r' | '\t' => {}
-
-            // new lines
-            '\n' => self.increase_line(),
+            // ignore whitespace, new lines are counted in `advance`
+            ' ' | '\r' | '\t' => {
+                self.advance_bytes_while(|b| matches!(b, b' ' | b'\r' | b'\t'));
+                self.trivia(TriviaKind::Whitespace)
+            }
+            '\n' => self.trivia(TriviaKind::Newline),
 
             // string literals
-            '"' => self.add_string(),
+            '"' => self.scan_string(false),
 
             // catch-all unsupported tokens
             _ => {
                 if c.is_ascii_digit() {
-                    self.add_number();
-                } else if c.is_alphabetic() || c == '_' {
-                    self.add_identifier(c);
+                    self.scan_number(c)
+                } else if unicode::is_xid_start(c) || c == '_' {
+                    self.scan_identifier()
                 } else {
-                    self.has_error = true;
-                    eprintln!(
-                        "[line {}] Error: Unexpected character: {}",
-                        self.line.get(),
-                        c
-                    );
+                    Some(Err(ScanError::UnexpectedCharacter {
+                        character: c,
+                        span: self.span(),
+                    }))
                 }
             }
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Result<&[Token], &[Token]> {
-        while !self.c_iter.peek().is_none() {
+    /// Scans the whole source, returning the tokens together with any
+    /// problems found along the way. Scanning does not stop at the first
+    /// error, so both may be non-empty.
+    pub fn scan_tokens(&mut self) -> (Vec<Token<'a>>, Vec<ScanError>) {
+        let mut tokens = vec![];
+        let mut diagnostics = vec![];
+
+        for result in self {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(error) => diagnostics.push(error),
+            }
+        }
+
+        (tokens, diagnostics)
+    }
+}
+
+/// Yields tokens lazily, one lexeme at a time. Errors are yielded in place
+/// and scanning resumes after them. The last item is always a single
+/// `TokenType::Eof` token.
+impl<'a> Iterator for Scanner<'a, '_> {
+    type Item = Result<Token<'a>, ScanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.scanned_preamble {
             self.start = self.current;
-            self.scan_token();
+            self.start_pos = self.current_pos();
+            self.scan_preamble();
         }
 
-        match self.has_error {
-            false => Ok(&self.tokens),
-            _ => Err(&self.tokens),
+        loop {
+            self.start = self.current;
+            self.start_pos = self.current_pos();
+
+            let result = if self.peek().is_none() {
+                if let Some(interpolation) = self.interpolations.pop() {
+                    // the innermost interpolation is reported, the ones
+                    // enclosing it are unterminated as a consequence
+                    self.interpolations.clear();
+                    return Some(Err(ScanError::UnterminatedInterpolation {
+                        span: interpolation.open,
+                    }));
+                }
+
+                if self.emitted_eof {
+                    return None;
+                }
+
+                self.emitted_eof = true;
+                self.token_without_literal(TokenType::Eof)
+            } else {
+                self.scan_token()
+            };
+
+            match result {
+                Some(Ok(token)) if self.keep_trivia => return Some(Ok(self.attach_trivia(token))),
+                Some(Err(error)) if self.keep_trivia => {
+                    self.trivia(TriviaKind::Skipped);
+                    return Some(Err(error));
+                }
+                Some(result) => return Some(result),
+                None => {}
+            }
         }
     }
 }
 
-impl<'a> From<&'a str> for Scanner<'a> {
+impl FusedIterator for Scanner<'_, '_> {}
+
+impl<'a> From<&'a str> for Scanner<'a, 'static> {
     fn from(value: &'a str) -> Self {
         Scanner::new(value)
     }
@@ -256,17 +754,29 @@ impl<'a> From<&'a str> for Scanner<'a> {
 mod tests {
     use super::*;
 
+    /// Span of a lexeme on the first line of an ASCII-only source.
+    fn span(start: usize, end: usize) -> Span {
+        let column = |offset: usize| NonZeroUsize::new(offset + 1).unwrap();
+        Span::new(
+            start,
+            end,
+            Position::new(NonZeroUsize::MIN, column(start)),
+            Position::new(NonZeroUsize::MIN, column(end)),
+        )
+    }
+
     #[test]
     fn test_empty_source() {
         let contents = "";
         let mut scanner = Scanner::from(contents);
 
-        let tokens = scanner.scan_tokens().unwrap();
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
         let expected_tokens = [Token::new(
             TokenType::Eof,
             String::from(""),
             Literal::None,
-            NonZeroUsize::new(1).unwrap(),
+            span(0, 0),
         )];
         for (i, token) in tokens.iter().enumerate() {
             assert_eq!(*token, expected_tokens[i])
@@ -278,32 +788,28 @@ mod tests {
         let contents = "(()";
         let mut scanner = Scanner::from(contents);
 
-        let tokens = scanner.scan_tokens().unwrap();
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
         let expected_tokens = [
             Token::new(
                 TokenType::LeftParen,
                 String::from("("),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(0, 1),
             ),
             Token::new(
                 TokenType::LeftParen,
                 String::from("("),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(1, 2),
             ),
             Token::new(
                 TokenType::RightParen,
                 String::from(")"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
-            ),
-            Token::new(
-                TokenType::Eof,
-                String::from(""),
-                Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(2, 3),
             ),
+            Token::new(TokenType::Eof, String::from(""), Literal::None, span(3, 3)),
         ];
         for (i, token) in tokens.iter().enumerate() {
             assert_eq!(*token, expected_tokens[i])
@@ -315,38 +821,34 @@ mod tests {
         let contents = "{{}}";
         let mut scanner = Scanner::from(contents);
 
-        let tokens = scanner.scan_tokens().unwrap();
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
         let expected_tokens = [
             Token::new(
                 TokenType::LeftBrace,
                 String::from("{"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(0, 1),
             ),
             Token::new(
                 TokenType::LeftBrace,
                 String::from("{"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(1, 2),
             ),
             Token::new(
                 TokenType::RightBrace,
                 String::from("}"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(2, 3),
             ),
             Token::new(
                 TokenType::RightBrace,
                 String::from("}"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
-            ),
-            Token::new(
-                TokenType::Eof,
-                String::from(""),
-                Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(3, 4),
             ),
+            Token::new(TokenType::Eof, String::from(""), Literal::None, span(4, 4)),
         ];
         for (i, token) in tokens.iter().enumerate() {
             assert_eq!(*token, expected_tokens[i])
@@ -358,68 +860,59 @@ mod tests {
         let contents = "({*.,+*})";
         let mut scanner = Scanner::from(contents);
 
-        let tokens = scanner.scan_tokens().unwrap();
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
         let expected_tokens = [
             Token::new(
                 TokenType::LeftParen,
                 String::from("("),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(0, 1),
             ),
             Token::new(
                 TokenType::LeftBrace,
                 String::from("{"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(1, 2),
             ),
             Token::new(
                 TokenType::Star,
                 String::from("*"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
-            ),
-            Token::new(
-                TokenType::Dot,
-                String::from("."),
-                Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(2, 3),
             ),
+            Token::new(TokenType::Dot, String::from("."), Literal::None, span(3, 4)),
             Token::new(
                 TokenType::Comma,
                 String::from(","),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(4, 5),
             ),
             Token::new(
                 TokenType::Plus,
                 String::from("+"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(5, 6),
             ),
             Token::new(
                 TokenType::Star,
                 String::from("*"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(6, 7),
             ),
             Token::new(
                 TokenType::RightBrace,
                 String::from("}"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(7, 8),
             ),
             Token::new(
                 TokenType::RightParen,
                 String::from(")"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
-            ),
-            Token::new(
-                TokenType::Eof,
-                String::from(""),
-                Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(8, 9),
             ),
+            Token::new(TokenType::Eof, String::from(""), Literal::None, span(9, 9)),
         ];
         for (i, token) in tokens.iter().enumerate() {
             assert_eq!(*token, expected_tokens[i])
@@ -431,43 +924,55 @@ mod tests {
         let contents = ",.$(#";
         let mut scanner = Scanner::from(contents);
 
-        let res = scanner.scan_tokens();
+        let (tokens, diagnostics) = scanner.scan_tokens();
         let expected_tokens = [
             Token::new(
                 TokenType::Comma,
                 String::from(","),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
-            ),
-            Token::new(
-                TokenType::Dot,
-                String::from("."),
-                Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(0, 1),
             ),
+            Token::new(TokenType::Dot, String::from("."), Literal::None, span(1, 2)),
             Token::new(
                 TokenType::LeftParen,
                 String::from("("),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
-            ),
-            Token::new(
-                TokenType::Eof,
-                String::from(""),
-                Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(3, 4),
             ),
+            Token::new(TokenType::Eof, String::from(""), Literal::None, span(5, 5)),
         ];
-        match res {
-            Err(tokens) => {
-                for (i, token) in tokens.iter().enumerate() {
-                    assert_eq!(*token, expected_tokens[i])
-                }
-            }
-            _ => {
-                res.expect_err("Test is incorrect, this should be an error");
-            }
+        for (i, token) in tokens.iter().enumerate() {
+            assert_eq!(*token, expected_tokens[i])
         }
+
+        let expected_diagnostics = [
+            ScanError::UnexpectedCharacter {
+                character: '$',
+                span: span(2, 3),
+            },
+            ScanError::UnexpectedCharacter {
+                character: '#',
+                span: span(4, 5),
+            },
+        ];
+        assert_eq!(diagnostics, expected_diagnostics);
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let contents = "(\n\"foo";
+        let mut scanner = Scanner::from(contents);
+
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(matches!(
+            diagnostics[0],
+            ScanError::UnterminatedString { .. }
+        ));
+        assert_eq!(diagnostics[0].span().range(), 2..6);
+        assert_eq!(diagnostics[0].line().get(), 2);
+        assert_eq!(diagnostics[0].to_string(), "Unterminated string.");
     }
 
     #[test]
@@ -475,38 +980,34 @@ mod tests {
         let contents = "=(==)";
         let mut scanner = Scanner::from(contents);
 
-        let tokens = scanner.scan_tokens().unwrap();
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
         let expected_tokens = [
             Token::new(
                 TokenType::Equal,
                 String::from("="),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(0, 1),
             ),
             Token::new(
                 TokenType::LeftParen,
                 String::from("("),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(1, 2),
             ),
             Token::new(
                 TokenType::EqualEqual,
                 String::from("=="),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(2, 4),
             ),
             Token::new(
                 TokenType::RightParen,
                 String::from(")"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
-            ),
-            Token::new(
-                TokenType::Eof,
-                String::from(""),
-                Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(4, 5),
             ),
+            Token::new(TokenType::Eof, String::from(""), Literal::None, span(5, 5)),
         ];
         for (i, token) in tokens.iter().enumerate() {
             assert_eq!(*token, expected_tokens[i])
@@ -514,124 +1015,395 @@ mod tests {
     }
 
     #[test]
-    fn test_ignore_comment() {
-        let contents = "(//this is a comment";
+    fn test_arithmetic_operators() {
+        let contents = "%(**)*";
         let mut scanner = Scanner::from(contents);
 
-        let tokens = scanner.scan_tokens().unwrap();
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
         let expected_tokens = [
+            Token::new(
+                TokenType::Percent,
+                String::from("%"),
+                Literal::None,
+                span(0, 1),
+            ),
             Token::new(
                 TokenType::LeftParen,
                 String::from("("),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(1, 2),
             ),
             Token::new(
-                TokenType::Eof,
-                String::from(""),
+                TokenType::StarStar,
+                String::from("**"),
+                Literal::None,
+                span(2, 4),
+            ),
+            Token::new(
+                TokenType::RightParen,
+                String::from(")"),
+                Literal::None,
+                span(4, 5),
+            ),
+            Token::new(
+                TokenType::Star,
+                String::from("*"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(5, 6),
             ),
+            Token::new(TokenType::Eof, String::from(""), Literal::None, span(6, 6)),
         ];
+        assert_eq!(tokens.len(), expected_tokens.len());
         for (i, token) in tokens.iter().enumerate() {
             assert_eq!(*token, expected_tokens[i])
         }
     }
 
     #[test]
-    fn test_unicode() {
-        let contents = "(///Unicode:£§᯽☺♣)";
+    fn test_compound_assignment_and_increment() {
+        let contents = "+=(-=)*=/=++--";
         let mut scanner = Scanner::from(contents);
 
-        let tokens = scanner.scan_tokens().unwrap();
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
         let expected_tokens = [
+            Token::new(
+                TokenType::PlusEqual,
+                String::from("+="),
+                Literal::None,
+                span(0, 2),
+            ),
             Token::new(
                 TokenType::LeftParen,
                 String::from("("),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(2, 3),
             ),
             Token::new(
-                TokenType::Eof,
-                String::from(""),
+                TokenType::MinusEqual,
+                String::from("-="),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(3, 5),
             ),
-        ];
-        for (i, token) in tokens.iter().enumerate() {
-            assert_eq!(*token, expected_tokens[i])
-        }
-    }
-
-    #[test]
-    fn test_ignore_whitespace() {
-        let contents = " /  \t \r";
-        let mut scanner = Scanner::from(contents);
-
-        let tokens = scanner.scan_tokens().unwrap();
-        let expected_tokens = [
             Token::new(
-                TokenType::Slash,
-                String::from("/"),
+                TokenType::RightParen,
+                String::from(")"),
+                Literal::None,
+                span(5, 6),
+            ),
+            Token::new(
+                TokenType::StarEqual,
+                String::from("*="),
+                Literal::None,
+                span(6, 8),
+            ),
+            Token::new(
+                TokenType::SlashEqual,
+                String::from("/="),
+                Literal::None,
+                span(8, 10),
+            ),
+            Token::new(
+                TokenType::PlusPlus,
+                String::from("++"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(10, 12),
+            ),
+            Token::new(
+                TokenType::MinusMinus,
+                String::from("--"),
+                Literal::None,
+                span(12, 14),
             ),
             Token::new(
                 TokenType::Eof,
                 String::from(""),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(14, 14),
             ),
         ];
+        assert_eq!(tokens.len(), expected_tokens.len());
         for (i, token) in tokens.iter().enumerate() {
             assert_eq!(*token, expected_tokens[i])
         }
     }
 
     #[test]
-    fn test_string_literal() {
-        let contents = "\"foo bar\"";
+    fn test_operator_maximal_munch() {
+        let contents = "+++---***=//=";
         let mut scanner = Scanner::from(contents);
 
-        let tokens = scanner.scan_tokens().unwrap();
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
         let expected_tokens = [
             Token::new(
-                TokenType::String,
-                String::from("\"foo bar\""),
-                Literal::String("foo bar".to_string()),
-                NonZeroUsize::new(1).unwrap(),
+                TokenType::PlusPlus,
+                String::from("++"),
+                Literal::None,
+                span(0, 2),
             ),
             Token::new(
-                TokenType::Eof,
-                String::from(""),
+                TokenType::Plus,
+                String::from("+"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(2, 3),
+            ),
+            Token::new(
+                TokenType::MinusMinus,
+                String::from("--"),
+                Literal::None,
+                span(3, 5),
+            ),
+            Token::new(
+                TokenType::Minus,
+                String::from("-"),
+                Literal::None,
+                span(5, 6),
+            ),
+            Token::new(
+                TokenType::StarStar,
+                String::from("**"),
+                Literal::None,
+                span(6, 8),
+            ),
+            Token::new(
+                TokenType::StarEqual,
+                String::from("*="),
+                Literal::None,
+                span(8, 10),
+            ),
+            Token::new(
+                TokenType::Eof,
+                String::from(""),
+                Literal::None,
+                span(13, 13),
             ),
         ];
+        assert_eq!(tokens.len(), expected_tokens.len());
         for (i, token) in tokens.iter().enumerate() {
             assert_eq!(*token, expected_tokens[i])
         }
     }
 
     #[test]
-    fn test_numbers() {
-        let contents = "1234.1234";
+    fn test_brackets_and_ternary() {
+        let contents = "[a?:]";
         let mut scanner = Scanner::from(contents);
 
-        let tokens = scanner.scan_tokens().unwrap();
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
         let expected_tokens = [
             Token::new(
-                TokenType::Number,
-                String::from("1234.1234"),
-                Literal::Number(1234.1234),
-                NonZeroUsize::new(1).unwrap(),
+                TokenType::LeftBracket,
+                String::from("["),
+                Literal::None,
+                span(0, 1),
+            ),
+            Token::new(
+                TokenType::Identifier,
+                String::from("a"),
+                Literal::None,
+                span(1, 2),
+            ),
+            Token::new(
+                TokenType::Question,
+                String::from("?"),
+                Literal::None,
+                span(2, 3),
+            ),
+            Token::new(
+                TokenType::Colon,
+                String::from(":"),
+                Literal::None,
+                span(3, 4),
+            ),
+            Token::new(
+                TokenType::RightBracket,
+                String::from("]"),
+                Literal::None,
+                span(4, 5),
+            ),
+            Token::new(TokenType::Eof, String::from(""), Literal::None, span(5, 5)),
+        ];
+        assert_eq!(tokens.len(), expected_tokens.len());
+        for (i, token) in tokens.iter().enumerate() {
+            assert_eq!(*token, expected_tokens[i])
+        }
+    }
+
+    #[test]
+    fn test_arrows() {
+        let contents = "->=>--=>==>";
+        let mut scanner = Scanner::from(contents);
+
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
+        let expected_tokens = [
+            Token::new(
+                TokenType::Arrow,
+                String::from("->"),
+                Literal::None,
+                span(0, 2),
+            ),
+            Token::new(
+                TokenType::FatArrow,
+                String::from("=>"),
+                Literal::None,
+                span(2, 4),
+            ),
+            Token::new(
+                TokenType::MinusMinus,
+                String::from("--"),
+                Literal::None,
+                span(4, 6),
+            ),
+            Token::new(
+                TokenType::FatArrow,
+                String::from("=>"),
+                Literal::None,
+                span(6, 8),
+            ),
+            Token::new(
+                TokenType::EqualEqual,
+                String::from("=="),
+                Literal::None,
+                span(8, 10),
+            ),
+            Token::new(
+                TokenType::Greater,
+                String::from(">"),
+                Literal::None,
+                span(10, 11),
+            ),
+            Token::new(
+                TokenType::Eof,
+                String::from(""),
+                Literal::None,
+                span(11, 11),
+            ),
+        ];
+        assert_eq!(tokens.len(), expected_tokens.len());
+        for (i, token) in tokens.iter().enumerate() {
+            assert_eq!(*token, expected_tokens[i])
+        }
+    }
+
+    #[test]
+    fn test_ignore_comment() {
+        let contents = "(//this is a comment";
+        let mut scanner = Scanner::from(contents);
+
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
+        let expected_tokens = [
+            Token::new(
+                TokenType::LeftParen,
+                String::from("("),
+                Literal::None,
+                span(0, 1),
+            ),
+            Token::new(
+                TokenType::Eof,
+                String::from(""),
+                Literal::None,
+                span(20, 20),
+            ),
+        ];
+        for (i, token) in tokens.iter().enumerate() {
+            assert_eq!(*token, expected_tokens[i])
+        }
+    }
+
+    #[test]
+    fn test_unicode() {
+        let contents = "(///Unicode:£§᯽☺♣)";
+        let mut scanner = Scanner::from(contents);
+
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
+        let expected_tokens = [
+            Token::new(
+                TokenType::LeftParen,
+                String::from("("),
+                Literal::None,
+                span(0, 1),
             ),
             Token::new(
                 TokenType::Eof,
                 String::from(""),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                // 26 bytes, but only 18 characters
+                Span::new(
+                    26,
+                    26,
+                    Position::new(NonZeroUsize::MIN, NonZeroUsize::new(19).unwrap()),
+                    Position::new(NonZeroUsize::MIN, NonZeroUsize::new(19).unwrap()),
+                ),
+            ),
+        ];
+        for (i, token) in tokens.iter().enumerate() {
+            assert_eq!(*token, expected_tokens[i])
+        }
+    }
+
+    #[test]
+    fn test_ignore_whitespace() {
+        let contents = " /  \t \r";
+        let mut scanner = Scanner::from(contents);
+
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
+        let expected_tokens = [
+            Token::new(
+                TokenType::Slash,
+                String::from("/"),
+                Literal::None,
+                span(1, 2),
+            ),
+            Token::new(TokenType::Eof, String::from(""), Literal::None, span(7, 7)),
+        ];
+        for (i, token) in tokens.iter().enumerate() {
+            assert_eq!(*token, expected_tokens[i])
+        }
+    }
+
+    #[test]
+    fn test_string_literal() {
+        let contents = "\"foo bar\"";
+        let mut scanner = Scanner::from(contents);
+
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
+        let expected_tokens = [
+            Token::new(
+                TokenType::String,
+                String::from("\"foo bar\""),
+                Literal::String(Cow::Borrowed("foo bar")),
+                span(0, 9),
+            ),
+            Token::new(TokenType::Eof, String::from(""), Literal::None, span(9, 9)),
+        ];
+        for (i, token) in tokens.iter().enumerate() {
+            assert_eq!(*token, expected_tokens[i])
+        }
+    }
+
+    #[test]
+    fn test_numbers() {
+        let contents = "1234.1234";
+        let mut scanner = Scanner::from(contents);
+
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
+        let expected_tokens = [
+            Token::new(
+                TokenType::Number,
+                String::from("1234.1234"),
+                Literal::Float(1234.1234),
+                span(0, 9),
             ),
+            Token::new(TokenType::Eof, String::from(""), Literal::None, span(9, 9)),
         ];
         for (i, token) in tokens.iter().enumerate() {
             assert_eq!(*token, expected_tokens[i])
@@ -643,31 +1415,32 @@ mod tests {
         let contents = "foo bar _hello";
         let mut scanner = Scanner::from(contents);
 
-        let tokens = scanner.scan_tokens().unwrap();
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
         let expected_tokens = [
             Token::new(
                 TokenType::Identifier,
                 String::from("foo"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(0, 3),
             ),
             Token::new(
                 TokenType::Identifier,
                 String::from("bar"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(4, 7),
             ),
             Token::new(
                 TokenType::Identifier,
                 String::from("_hello"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(8, 14),
             ),
             Token::new(
                 TokenType::Eof,
                 String::from(""),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(14, 14),
             ),
         ];
         for (i, token) in tokens.iter().enumerate() {
@@ -680,23 +1453,722 @@ mod tests {
         let contents = "and";
         let mut scanner = Scanner::from(contents);
 
-        let tokens = scanner.scan_tokens().unwrap();
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
         let expected_tokens = [
             Token::new(
                 TokenType::And,
                 String::from("and"),
                 Literal::None,
-                NonZeroUsize::new(1).unwrap(),
-            ),
-            Token::new(
-                TokenType::Eof,
-                String::from(""),
-                Literal::None,
-                NonZeroUsize::new(1).unwrap(),
+                span(0, 3),
             ),
+            Token::new(TokenType::Eof, String::from(""), Literal::None, span(3, 3)),
         ];
         for (i, token) in tokens.iter().enumerate() {
             assert_eq!(*token, expected_tokens[i])
         }
     }
+
+    #[test]
+    fn test_multi_line_string_span() {
+        let contents = "(\n\"foo\nbar\" baz";
+        let mut scanner = Scanner::from(contents);
+
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
+        let pos = |line: usize, column: usize| {
+            Position::new(
+                NonZeroUsize::new(line).unwrap(),
+                NonZeroUsize::new(column).unwrap(),
+            )
+        };
+
+        assert_eq!(tokens[1].span(), Span::new(2, 11, pos(2, 1), pos(3, 5)));
+        assert_eq!(tokens[1].line().get(), 2);
+        assert_eq!(tokens[2].span(), Span::new(12, 15, pos(3, 6), pos(3, 9)));
+        assert_eq!(&contents[tokens[2].span().range()], "baz");
+    }
+
+    #[test]
+    fn test_non_ascii_identifier_span() {
+        let contents = "größe = π";
+        let mut scanner = Scanner::from(contents);
+
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
+        let pos =
+            |column: usize| Position::new(NonZeroUsize::MIN, NonZeroUsize::new(column).unwrap());
+
+        // "größe" is 5 characters but 7 bytes
+        assert_eq!(tokens[0].span(), Span::new(0, 7, pos(1), pos(6)));
+        assert_eq!(tokens[1].span(), Span::new(8, 9, pos(7), pos(8)));
+        assert_eq!(tokens[2].span(), Span::new(10, 12, pos(9), pos(10)));
+        assert_eq!(&contents[tokens[2].span().range()], "π");
+    }
+
+    #[test]
+    fn test_non_ascii_text_is_counted_in_characters() {
+        // strings and comments are skipped over as bytes
+        let contents = "\"ünï\" /* ☕ */ x // é\n\"\\tö\" y";
+        let mut scanner = Scanner::from(contents);
+
+        let (tokens, diagnostics) = scanner.scan_tokens();
+        assert!(diagnostics.is_empty());
+        let pos = |line: usize, column: usize| {
+            Position::new(
+                NonZeroUsize::new(line).unwrap(),
+                NonZeroUsize::new(column).unwrap(),
+            )
+        };
+
+        assert_eq!(tokens[0].literal(), &Literal::String("ünï".into()));
+        assert_eq!(tokens[0].span().end_pos, pos(1, 6));
+        assert_eq!(tokens[1].span().start_pos, pos(1, 15));
+        assert_eq!(tokens[2].literal(), &Literal::String("\tö".into()));
+        assert_eq!(tokens[3].span().start_pos, pos(2, 7));
+    }
+
+    #[test]
+    fn test_iterator_yields_lazily_and_ends_with_eof() {
+        let contents = "( $ )";
+        let mut scanner = Scanner::from(contents);
+
+        assert_eq!(scanner.next().unwrap().unwrap().span(), span(0, 1));
+        // nothing past the current lexeme has been consumed yet
+        assert_eq!(scanner.current, 1);
+
+        assert!(matches!(
+            scanner.next(),
+            Some(Err(ScanError::UnexpectedCharacter { character: '$', .. }))
+        ));
+        assert_eq!(scanner.next().unwrap().unwrap().span(), span(4, 5));
+        assert_eq!(
+            scanner.next().unwrap().unwrap(),
+            Token::new(TokenType::Eof, String::from(""), Literal::None, span(5, 5))
+        );
+        assert!(scanner.next().is_none());
+        assert!(scanner.next().is_none());
+    }
+
+    #[test]
+    fn test_eof_is_emitted_once() {
+        for contents in ["", "  ", "// comment", "(", "\"unterminated"] {
+            let eofs = Scanner::from(contents)
+                .filter_map(Result::ok)
+                .filter(|token| token.kind() == TokenType::Eof)
+                .count();
+            assert_eq!(eofs, 1, "source: {contents:?}");
+        }
+    }
+
+    /// Every source used by the tests above, plus a few covering trivia.
+    const ROUND_TRIP_SOURCES: &[&str] = &[
+        "",
+        "(()",
+        "{{}}",
+        "({*.,+*})",
+        ",.$(#",
+        "=(==)",
+        "(//this is a comment",
+        "(///Unicode:£§᯽☺♣)",
+        " /  \t \r",
+        "\"foo bar\"",
+        "1234.1234",
+        "foo bar _hello",
+        "and",
+        "(\n\"foo\nbar\" baz",
+        "größe = π",
+        "( $ )",
+        "(\n\"foo",
+        "var a = 1; // one\r\n\n  print a;\t// done\n",
+        "a /* one\n /* two */ */ b /* three",
+        "\"tab\\t\\\"quoted\\\" \\u{1F600}\" \"bad \\q\"",
+        "\"a ${ {x} } b ${ \"c ${d}\" } e\" \"f ${",
+        "\u{FEFF}#!/usr/bin/env lox\nprint 1;",
+    ];
+
+    fn reconstruct(tokens: &[Token]) -> String {
+        let mut source = String::new();
+        for token in tokens {
+            for trivia in token.leading_trivia() {
+                source.push_str(trivia.text());
+            }
+            source.push_str(token.lexeme());
+            for trivia in token.trailing_trivia() {
+                source.push_str(trivia.text());
+            }
+        }
+        source
+    }
+
+    #[test]
+    fn test_trivia_round_trip() {
+        for contents in ROUND_TRIP_SOURCES {
+            let (tokens, _) = Scanner::from(*contents).with_trivia().scan_tokens();
+            assert_eq!(reconstruct(&tokens), *contents);
+
+            // trivia does not change the tokens themselves
+            let (plain, _) = Scanner::from(*contents).scan_tokens();
+            assert_eq!(tokens.len(), plain.len());
+            for (token, plain) in tokens.iter().zip(plain.iter()) {
+                assert_eq!(token.to_string(), plain.to_string());
+                assert_eq!(token.span(), plain.span());
+            }
+        }
+    }
+
+    #[test]
+    fn test_trivia_attachment() {
+        let contents = "a // one\n  b";
+        let (tokens, _) = Scanner::from(contents).with_trivia().scan_tokens();
+
+        let kinds = |trivia: &[Trivia]| trivia.iter().map(Trivia::kind).collect::<Vec<_>>();
+        assert!(tokens[0].leading_trivia().is_empty());
+        assert_eq!(
+            kinds(tokens[0].trailing_trivia()),
+            [TriviaKind::Whitespace, TriviaKind::LineComment]
+        );
+        assert_eq!(
+            kinds(tokens[1].leading_trivia()),
+            [TriviaKind::Newline, TriviaKind::Whitespace]
+        );
+        assert_eq!(tokens[1].leading_trivia()[1].span().range(), 9..11);
+        assert!(tokens[1].trailing_trivia().is_empty());
+    }
+
+    #[test]
+    fn test_trivia_is_off_by_default() {
+        let (tokens, _) = Scanner::from(" a // one\n").scan_tokens();
+        assert!(tokens
+            .iter()
+            .all(|t| t.leading_trivia().is_empty() && t.trailing_trivia().is_empty()));
+    }
+
+    #[test]
+    fn test_nested_block_comments() {
+        let contents = "(/* outer /* inner\n */ still a comment\n*/)";
+        let (tokens, diagnostics) = Scanner::from(contents).scan_tokens();
+        assert!(diagnostics.is_empty());
+
+        let pos = |line: usize, column: usize| {
+            Position::new(
+                NonZeroUsize::new(line).unwrap(),
+                NonZeroUsize::new(column).unwrap(),
+            )
+        };
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[1].lexeme(), ")");
+        assert_eq!(tokens[1].span(), Span::new(41, 42, pos(3, 3), pos(3, 4)));
+    }
+
+    #[test]
+    fn test_block_comment_is_not_a_slash() {
+        let (tokens, diagnostics) = Scanner::from("a/**/ / b").scan_tokens();
+        assert!(diagnostics.is_empty());
+        let lexemes = tokens.iter().map(Token::lexeme).collect::<Vec<_>>();
+        assert_eq!(lexemes, ["a", "/", "b", ""]);
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let contents = "(\n  /* one /* two */\n";
+        let (tokens, diagnostics) = Scanner::from(contents).scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
+        let pos = |line: usize, column: usize| {
+            Position::new(
+                NonZeroUsize::new(line).unwrap(),
+                NonZeroUsize::new(column).unwrap(),
+            )
+        };
+        assert_eq!(
+            diagnostics,
+            [ScanError::UnterminatedBlockComment {
+                span: Span::new(4, 6, pos(2, 3), pos(2, 5)),
+            }]
+        );
+        assert_eq!(diagnostics[0].line().get(), 2);
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let contents = r#""a\"b\\c\n\t\r\0\u{41}\u{1F600}""#;
+        let (tokens, diagnostics) = Scanner::from(contents).scan_tokens();
+        assert!(diagnostics.is_empty());
+
+        let expected = Token::new(
+            TokenType::String,
+            contents,
+            Literal::String(Cow::Owned(String::from("a\"b\\c\n\t\r\0A😀"))),
+            span(0, contents.len()),
+        );
+        assert_eq!(tokens[0], expected);
+        assert_eq!(tokens[0].lexeme(), contents);
+    }
+
+    #[test]
+    fn test_string_without_escapes_is_borrowed() {
+        let (tokens, _) = Scanner::from("\"plain\"").scan_tokens();
+        let expected = Token::new(
+            TokenType::String,
+            "\"plain\"",
+            Literal::String(Cow::Borrowed("plain")),
+            span(0, 7),
+        );
+        assert_eq!(tokens[0], expected);
+    }
+
+    #[test]
+    fn test_invalid_escapes() {
+        let cases = [
+            (r#"x = "ab\q";"#, r"\q", 7..9),
+            (r#""\u{110000}""#, r"\u{110000}", 1..11),
+            (r#""\u{}""#, r"\u{}", 1..5),
+            (r#""\u41""#, r"\u", 1..3),
+            (r#""\u{1234567}""#, r"\u{1234567}", 1..12),
+        ];
+
+        for (contents, sequence, range) in cases {
+            let (tokens, diagnostics) = Scanner::from(contents).scan_tokens();
+            assert!(tokens.iter().all(|t| !t.lexeme().starts_with('"')));
+            assert_eq!(diagnostics.len(), 1, "source: {contents}");
+            assert_eq!(
+                diagnostics[0],
+                ScanError::InvalidEscape {
+                    sequence: sequence.to_string(),
+                    span: span(range.start, range.end),
+                }
+            );
+        }
+
+        // scanning continues after the closing quote
+        let (tokens, _) = Scanner::from(r#""\q" x"#).scan_tokens();
+        assert_eq!(tokens[0].lexeme(), "x");
+    }
+
+    #[test]
+    fn test_escaped_backslash_before_end_of_input() {
+        let (_, diagnostics) = Scanner::from(r#""abc\"#).scan_tokens();
+        assert!(matches!(
+            diagnostics[..],
+            [ScanError::UnterminatedString { .. }]
+        ));
+    }
+
+    fn kinds_and_literals(contents: &str) -> Vec<String> {
+        let (tokens, diagnostics) = Scanner::from(contents).scan_tokens();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        tokens.iter().map(Token::to_string).collect()
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        assert_eq!(
+            kinds_and_literals(r#""total: ${a + b}!""#),
+            [
+                "STRING_START \"total: ${ total: ",
+                "IDENTIFIER a null",
+                "PLUS + null",
+                "IDENTIFIER b null",
+                "STRING_END }!\" !",
+                "EOF  null",
+            ]
+        );
+        assert_eq!(
+            kinds_and_literals(r#""${x}-${y}""#),
+            [
+                "STRING_START \"${ ",
+                "IDENTIFIER x null",
+                "STRING_MIDDLE }-${ -",
+                "IDENTIFIER y null",
+                "STRING_END }\" ",
+                "EOF  null",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_string_interpolation() {
+        assert_eq!(
+            kinds_and_literals(r#""a ${ {x} } b ${ "c ${d}" } e""#),
+            [
+                "STRING_START \"a ${ a ",
+                "LEFT_BRACE { null",
+                "IDENTIFIER x null",
+                "RIGHT_BRACE } null",
+                "STRING_MIDDLE } b ${  b ",
+                "STRING_START \"c ${ c ",
+                "IDENTIFIER d null",
+                "STRING_END }\" ",
+                "STRING_END } e\"  e",
+                "EOF  null",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interpolation_escapes() {
+        assert_eq!(
+            kinds_and_literals(r#""\${x} $x {}""#),
+            ["STRING \"\\${x} $x {}\" ${x} $x {}", "EOF  null"]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_interpolation() {
+        let (tokens, diagnostics) = Scanner::from(r#"("a ${ "b ${ c"#).scan_tokens();
+        assert_eq!(
+            diagnostics,
+            [ScanError::UnterminatedInterpolation { span: span(10, 12) }]
+        );
+        assert_eq!(tokens.last().unwrap().to_string(), "EOF  null");
+
+        let (_, diagnostics) = Scanner::from(r#""a ${b} c"#).scan_tokens();
+        assert!(matches!(
+            diagnostics[..],
+            [ScanError::UnterminatedString { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_extended_number_literals() {
+        let cases = [
+            ("0xFF", Literal::Integer(255)),
+            ("0Xff", Literal::Integer(255)),
+            ("0b1010", Literal::Integer(10)),
+            ("0o17", Literal::Integer(15)),
+            ("1e-9", Literal::Float(1e-9)),
+            ("6.02E23", Literal::Float(6.02e23)),
+            ("2e+3", Literal::Float(2000.0)),
+            ("1_000_000", Literal::Integer(1_000_000)),
+            ("0x_dead_BEEF", Literal::Integer(3_735_928_559)),
+            ("1_0.2_5e1_0", Literal::Float(10.25e10)),
+        ];
+
+        for (contents, literal) in cases {
+            let (tokens, diagnostics) = Scanner::from(contents).scan_tokens();
+            assert!(diagnostics.is_empty(), "source: {contents}");
+            assert_eq!(
+                tokens[0],
+                Token::new(
+                    TokenType::Number,
+                    contents,
+                    literal,
+                    span(0, contents.len())
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_number_followed_by_dot_or_identifier() {
+        assert_eq!(
+            kinds_and_literals("123.foo 0x1f.x 7ab 1else 2east"),
+            [
+                "NUMBER 123 123.0",
+                "DOT . null",
+                "IDENTIFIER foo null",
+                "NUMBER 0x1f 31.0",
+                "DOT . null",
+                "IDENTIFIER x null",
+                "NUMBER 7 7.0",
+                "IDENTIFIER ab null",
+                "NUMBER 1 1.0",
+                "ELSE else null",
+                "NUMBER 2 2.0",
+                "IDENTIFIER east null",
+                "EOF  null",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_malformed_numbers() {
+        let cases = [
+            ("0x", "missing digits after base prefix"),
+            ("0b_", "missing digits after base prefix"),
+            ("0b102", "invalid binary digit"),
+            ("0o8", "invalid octal digit"),
+            ("0xfg", "invalid hexadecimal digit"),
+            ("0x1_", "trailing underscore"),
+            ("1e", "missing exponent digits"),
+            ("1e+", "missing exponent digits"),
+            ("1_", "trailing underscore"),
+            ("1_.5", "trailing underscore"),
+            ("1.5_", "trailing underscore"),
+            ("1e5_", "trailing underscore"),
+            ("1e-", "missing exponent digits"),
+        ];
+
+        for (contents, reason) in cases {
+            let (tokens, diagnostics) = Scanner::from(contents).scan_tokens();
+            assert_eq!(tokens.len(), 1, "source: {contents}");
+            assert_eq!(
+                diagnostics,
+                [ScanError::MalformedNumber {
+                    reason,
+                    span: span(0, contents.len())
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn test_integer_and_float_literals() {
+        assert_eq!(
+            kinds_and_literals(
+                "42 42.0 9007199254740993 9999999999999999 10000000000000000 \
+                 9223372036854775807 1e3 0x10"
+            ),
+            [
+                "NUMBER 42 42.0",
+                "NUMBER 42.0 42.0",
+                // one past 2^53, which an f64 can not represent
+                "NUMBER 9007199254740993 9007199254740993.0",
+                "NUMBER 9999999999999999 9999999999999999.0",
+                // large integers print like the f64 they used to be
+                "NUMBER 10000000000000000 1e16",
+                "NUMBER 9223372036854775807 9.223372036854776e18",
+                "NUMBER 1e3 1000.0",
+                "NUMBER 0x10 16.0",
+                "EOF  null",
+            ]
+        );
+
+        let (tokens, _) = Scanner::from("9007199254740993").scan_tokens();
+        assert_eq!(
+            tokens[0],
+            Token::new(
+                TokenType::Number,
+                "9007199254740993",
+                Literal::Integer(9_007_199_254_740_993),
+                span(0, 16)
+            )
+        );
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        for contents in [
+            "9223372036854775808",
+            "0x8000000000000000",
+            "0b1_0000000000000000000000000000000000000000000000000000000000000000",
+        ] {
+            let (tokens, diagnostics) = Scanner::from(contents).scan_tokens();
+            assert_eq!(tokens.len(), 1);
+            assert_eq!(
+                diagnostics,
+                [ScanError::IntegerOverflow {
+                    span: span(0, contents.len())
+                }]
+            );
+        }
+
+        // floats are not limited to the integer range
+        let (_, diagnostics) = Scanner::from("9223372036854775808.0").scan_tokens();
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_xid_identifiers() {
+        assert_eq!(
+            kinds_and_literals("naïve_1 變數 переменная e\u{301}te"),
+            [
+                "IDENTIFIER naïve_1 null",
+                "IDENTIFIER 變數 null",
+                "IDENTIFIER переменная null",
+                // a combining mark continues an identifier
+                "IDENTIFIER e\u{301}te null",
+                "EOF  null",
+            ]
+        );
+
+        // alphabetic, but not XID_Start
+        let (_, diagnostics) = Scanner::from("\u{2E2F}").scan_tokens();
+        assert!(matches!(
+            diagnostics[..],
+            [ScanError::UnexpectedCharacter {
+                character: '\u{2E2F}',
+                ..
+            }]
+        ));
+
+        // a combining mark can not start one
+        let (_, diagnostics) = Scanner::from("\u{301}e").scan_tokens();
+        assert!(matches!(
+            diagnostics[..],
+            [ScanError::UnexpectedCharacter {
+                character: '\u{301}',
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_identifier_names_are_nfc_normalized() {
+        let (tokens, _) = Scanner::from("caf\u{e9} cafe\u{301}").scan_tokens();
+
+        assert_ne!(tokens[0].lexeme(), tokens[1].lexeme());
+        assert_eq!(tokens[0].name(), tokens[1].name());
+        assert!(matches!(tokens[0].name(), Cow::Borrowed(_)));
+        assert_eq!(tokens[1].name(), "caf\u{e9}");
+    }
+
+    #[test]
+    fn test_confusable_mixed_script_identifiers() {
+        // Cyrillic `а` (U+0430) in a Latin name
+        let contents = "p\u{430}ypal";
+        let (tokens, diagnostics) = Scanner::from(contents).scan_tokens();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(
+            diagnostics,
+            [ScanError::ConfusableIdentifier {
+                identifier: contents.to_string(),
+                span: Span::new(
+                    0,
+                    7,
+                    Position::default(),
+                    Position::new(NonZeroUsize::MIN, NonZeroUsize::new(7).unwrap()),
+                ),
+            }]
+        );
+
+        // scripts that are commonly written together are fine
+        let (_, diagnostics) = Scanner::from("変数の名前 カウント_1 Größe").scan_tokens();
+        assert!(diagnostics.is_empty());
+
+        // so are names mixing scripts without lookalikes
+        for name in ["user名前", "café_日本", "größe_值", "x_переменная"] {
+            let (tokens, diagnostics) = Scanner::from(name).scan_tokens();
+            assert!(diagnostics.is_empty(), "{name}: {diagnostics:?}");
+            assert_eq!(tokens[0].lexeme(), name);
+        }
+
+        // a lookalike in a word of another script is still caught
+        for name in ["x_p\u{430}ypal", "\u{440}aypal1", "\u{43e}k_\u{43e}k"] {
+            let (_, diagnostics) = Scanner::from(name).scan_tokens();
+            assert_eq!(diagnostics.len(), 1, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_custom_dialect() {
+        let dialect = Dialect::lox()
+            .rename_keyword("var", "let")
+            .reserve("import")
+            .with_keyword("not", TokenType::Bang)
+            .disable(TokenType::Class)
+            .disable(TokenType::Star);
+
+        let contents = "let var import not class *";
+        let mut tokens = vec![];
+        let mut diagnostics = vec![];
+        for result in Scanner::from(contents).with_dialect(&dialect) {
+            match result {
+                Ok(token) => tokens.push(token.to_string()),
+                Err(error) => diagnostics.push(error),
+            }
+        }
+
+        assert_eq!(
+            tokens,
+            [
+                "VAR let null",
+                "IDENTIFIER var null",
+                "RESERVED import null",
+                "BANG not null",
+                "EOF  null",
+            ]
+        );
+        assert_eq!(
+            diagnostics,
+            [
+                ScanError::DisabledToken {
+                    lexeme: String::from("class"),
+                    span: span(19, 24),
+                },
+                ScanError::DisabledToken {
+                    lexeme: String::from("*"),
+                    span: span(25, 26),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_dialect_is_lox() {
+        let dialect = Dialect::default();
+        let contents = "class let import";
+
+        let (default, _) = Scanner::from(contents).scan_tokens();
+        let (explicit, _) = Scanner::from(contents).with_dialect(&dialect).scan_tokens();
+        assert_eq!(default, explicit);
+        assert_eq!(default[1].to_string(), "IDENTIFIER let null");
+    }
+
+    #[test]
+    fn test_tokens_outlive_the_dialect() {
+        fn scan(contents: &str) -> Vec<Token<'_>> {
+            let dialect = Dialect::lox().reserve("let");
+            Scanner::from(contents)
+                .with_dialect(&dialect)
+                .scan_tokens()
+                .0
+        }
+
+        assert_eq!(scan("let")[0].to_string(), "RESERVED let null");
+    }
+
+    #[test]
+    fn test_shebang_and_bom() {
+        for contents in [
+            "#!/usr/bin/env lox\nprint x;",
+            "\u{FEFF}#!/usr/bin/env lox\nprint x;",
+            "\u{FEFF}\nprint x;",
+        ] {
+            let (tokens, diagnostics) = Scanner::from(contents).scan_tokens();
+            assert!(diagnostics.is_empty(), "source: {contents:?}");
+
+            let print = &tokens[0];
+            assert_eq!(print.to_string(), "PRINT print null");
+            assert_eq!(print.line().get(), 2);
+            assert_eq!(print.span().start_pos.column, NonZeroUsize::MIN);
+            assert_eq!(&contents[print.span().range()], "print");
+        }
+
+        // the mark does not take up a column
+        let (tokens, _) = Scanner::from("\u{FEFF}x").scan_tokens();
+        assert_eq!(tokens[0].span().start_pos, Position::default());
+        assert_eq!(tokens[0].span().range(), 3..4);
+    }
+
+    #[test]
+    fn test_shebang_trivia() {
+        let contents = "\u{FEFF}#!lox\nx";
+        let (tokens, _) = Scanner::from(contents).with_trivia().scan_tokens();
+
+        let kinds = tokens[0]
+            .leading_trivia()
+            .iter()
+            .map(Trivia::kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [TriviaKind::Bom, TriviaKind::Shebang, TriviaKind::Newline]
+        );
+        assert_eq!(tokens[0].leading_trivia()[1].text(), "#!lox");
+    }
+
+    #[test]
+    fn test_hash_outside_of_shebang_is_an_error() {
+        for contents in ["x\n#!lox", " #!lox", "#lox", "\u{FEFF}\u{FEFF}#!lox"] {
+            let (_, diagnostics) = Scanner::from(contents).scan_tokens();
+            assert!(
+                diagnostics
+                    .iter()
+                    .any(|e| matches!(e, ScanError::UnexpectedCharacter { .. })),
+                "source: {contents:?}"
+            );
+        }
+    }
 }
@@ -0,0 +1,215 @@
+use std::num::NonZeroUsize;
+
+use crate::syntax::scanner::Scanner;
+use crate::syntax::span::{Position, Span};
+
+/// Identifies a file registered in a [`SourceMap`].
+///
+/// Scanners that were not given an id use `SourceId::default()`, which is
+/// also the id of the first file added to a map.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, PartialOrd, Ord)]
+pub struct SourceId(u32);
+
+impl SourceId {
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A loaded source file and the byte offsets its lines start at.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    name: String,
+    text: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    fn new(name: String, text: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            name,
+            text,
+            line_starts,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The text of a 1-based line without its line ending, or `None` past
+    /// the last line.
+    pub fn line_text(&self, line: NonZeroUsize) -> Option<&str> {
+        let start = *self.line_starts.get(line.get() - 1)?;
+        let end = self
+            .line_starts
+            .get(line.get())
+            .map_or(self.text.len(), |next| next - 1);
+        let text = &self.text[start..end];
+        Some(text.strip_suffix('\r').unwrap_or(text))
+    }
+
+    /// The line and column of a byte offset, counted the way the scanner
+    /// counts them: columns are `char`s and a leading byte order mark does
+    /// not take one.
+    ///
+    /// # Panics
+    ///
+    /// If `offset` is past the end of the text or not on a `char` boundary.
+    pub fn position(&self, offset: usize) -> Position {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let mut start = self.line_starts[line - 1];
+        if line == 1 && self.text.starts_with('\u{feff}') && offset > 0 {
+            start = '\u{feff}'.len_utf8();
+        }
+        let column = self.text[start..offset].chars().count() + 1;
+
+        Position::new(
+            NonZeroUsize::new(line).unwrap(),
+            NonZeroUsize::new(column).unwrap(),
+        )
+    }
+}
+
+/// The files loaded in one session, so that spans from any of them can be
+/// traced back to a file name and the text around them.
+///
+/// ```
+/// use interpreter_starter_rust::SourceMap;
+///
+/// let mut sources = SourceMap::new();
+/// let id = sources.add("main.lox", "print 1;\nprint @;");
+/// let (_, diagnostics) = sources.scanner(id).scan_tokens();
+///
+/// let span = diagnostics[0].span();
+/// assert_eq!(sources.name(span), "main.lox");
+/// assert_eq!(sources.line_text(span), "print @;");
+/// assert_eq!(span.start_pos.column.get(), 7);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a file and returns the id its spans will carry.
+    pub fn add(&mut self, name: impl Into<String>, text: impl Into<String>) -> SourceId {
+        let id = SourceId(self.files.len() as u32);
+        self.files.push(SourceFile::new(name.into(), text.into()));
+        id
+    }
+
+    /// # Panics
+    ///
+    /// If `id` was not returned by this map.
+    pub fn file(&self, id: SourceId) -> &SourceFile {
+        &self.files[id.index()]
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (SourceId, &SourceFile)> {
+        (0..).map(SourceId).zip(&self.files)
+    }
+
+    /// A scanner over the file whose tokens and errors carry its id.
+    pub fn scanner(&self, id: SourceId) -> Scanner<'_, 'static> {
+        Scanner::from(self.file(id).text()).with_source(id)
+    }
+
+    /// The name of the file `span` is in.
+    pub fn name(&self, span: Span) -> &str {
+        self.file(span.source).name()
+    }
+
+    /// The full line `span` starts on.
+    pub fn line_text(&self, span: Span) -> &str {
+        self.file(span.source)
+            .line_text(span.start_pos.line)
+            .unwrap_or_default()
+    }
+
+    /// The position of `span`'s first character, recomputed from its byte
+    /// offset.
+    pub fn position(&self, span: Span) -> Position {
+        self.file(span.source).position(span.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(line: usize, column: usize) -> Position {
+        Position::new(
+            NonZeroUsize::new(line).unwrap(),
+            NonZeroUsize::new(column).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_line_text() {
+        let mut sources = SourceMap::new();
+        let id = sources.add("a.lox", "one\r\ntwo\n\nfour");
+        let file = sources.file(id);
+
+        let lines: Vec<_> = (1..=5)
+            .map(|line| file.line_text(NonZeroUsize::new(line).unwrap()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![Some("one"), Some("two"), Some(""), Some("four"), None]
+        );
+        assert_eq!(file.line_count(), 4);
+    }
+
+    #[test]
+    fn test_position_matches_scanner() {
+        let mut sources = SourceMap::new();
+        sources.add("empty.lox", "");
+        let id = sources.add("b.lox", "\u{feff}var é = \"ü\n\";\n  é;");
+
+        assert_eq!(sources.file(id).position(0), pos(1, 1));
+        for token in sources.scanner(id).scan_tokens().0 {
+            let span = token.span();
+            assert_eq!(span.source, id);
+            assert_eq!(sources.name(span), "b.lox");
+            assert_eq!(sources.position(span), span.start_pos, "{token}");
+        }
+    }
+
+    #[test]
+    fn test_spans_from_several_files() {
+        let mut sources = SourceMap::new();
+        let a = sources.add("a.lox", "print 1;");
+        let b = sources.add("b.lox", "\n\n  #");
+
+        let (_, diagnostics) = sources.scanner(b).scan_tokens();
+        let span = diagnostics[0].span();
+
+        assert_ne!(a, b);
+        assert_eq!(sources.name(span), "b.lox");
+        assert_eq!(sources.line_text(span), "  #");
+        assert_eq!(sources.position(span), pos(3, 3));
+        assert_eq!(
+            sources
+                .files()
+                .map(|(_, file)| file.name())
+                .collect::<Vec<_>>(),
+            vec!["a.lox", "b.lox"]
+        );
+    }
+}
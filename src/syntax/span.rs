@@ -0,0 +1,120 @@
+use std::num::NonZeroUsize;
+
+use crate::syntax::source::SourceId;
+
+/// A 1-based line/column pair. Columns count `char`s, not bytes, so a
+/// multi-byte character only advances the column by one.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Position {
+    pub line: NonZeroUsize,
+    pub column: NonZeroUsize,
+}
+
+impl Position {
+    pub fn new(line: NonZeroUsize, column: NonZeroUsize) -> Self {
+        Self { line, column }
+    }
+
+    /// Where this position ends up when the text in front of `from` is
+    /// edited so that `from` moves to `to`, assuming everything from `from`
+    /// up to this position is unchanged.
+    pub fn relocated(self, from: Position, to: Position) -> Position {
+        if self.line == from.line {
+            let column = self.column.get() - from.column.get() + to.column.get();
+            Position::new(to.line, NonZeroUsize::new(column).unwrap())
+        } else {
+            let line = self.line.get() - from.line.get() + to.line.get();
+            Position::new(NonZeroUsize::new(line).unwrap(), self.column)
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self {
+            line: NonZeroUsize::MIN,
+            column: NonZeroUsize::MIN,
+        }
+    }
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// The location of a lexeme in the source.
+///
+/// `start..end` is the byte range into the source string. `start_pos` is the
+/// position of the first character and `end_pos` the position just past the
+/// last one, so an empty span has `start_pos == end_pos`. `source` is the
+/// file in a [`SourceMap`](crate::SourceMap) the offsets refer to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub start_pos: Position,
+    pub end_pos: Position,
+    pub source: SourceId,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, start_pos: Position, end_pos: Position) -> Self {
+        Self {
+            start,
+            end,
+            start_pos,
+            end_pos,
+            source: SourceId::default(),
+        }
+    }
+
+    pub fn with_source(mut self, source: SourceId) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Moves the span along with the unchanged text starting at `from`,
+    /// which now starts at `to`, possibly in another source. See
+    /// [`Position::relocated`].
+    pub fn relocated(&self, from: Span, to: Span) -> Span {
+        Self {
+            start: self.start - from.start + to.start,
+            end: self.end - from.start + to.start,
+            start_pos: self.start_pos.relocated(from.start_pos, to.start_pos),
+            end_pos: self.end_pos.relocated(from.start_pos, to.start_pos),
+            source: to.source,
+        }
+    }
+
+    /// The empty span at the start of this one.
+    pub fn shrink_to_start(&self) -> Span {
+        Self {
+            end: self.start,
+            end_pos: self.start_pos,
+            ..*self
+        }
+    }
+
+    /// The empty span at the end of this one.
+    pub fn shrink_to_end(&self) -> Span {
+        Self {
+            start: self.end,
+            start_pos: self.end_pos,
+            ..*self
+        }
+    }
+}
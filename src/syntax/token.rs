@@ -1,6 +1,11 @@
+use std::borrow::Cow;
 use std::num::NonZeroUsize;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+use crate::syntax::source::SourceId;
+use crate::syntax::span::Span;
+use crate::syntax::unicode;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum TokenType {
     // single character tokens
     LeftParen,
@@ -13,6 +18,11 @@ pub enum TokenType {
     Plus,
     Semicolon,
     Star,
+    Percent,
+    LeftBracket,
+    RightBracket,
+    Colon,
+    Question,
 
     // one or two character tokens
     Bang,
@@ -24,11 +34,25 @@ pub enum TokenType {
     Greater,
     GreaterEqual,
     Slash,
+    StarStar,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PlusPlus,
+    MinusMinus,
+    Arrow,
+    FatArrow,
 
     // literals
     String,
     Number,
 
+    // parts of an interpolated string: `"a ${` `} b ${` `} c"`
+    StringStart,
+    StringMiddle,
+    StringEnd,
+
     // ID
     Identifier,
 
@@ -50,10 +74,113 @@ pub enum TokenType {
     Var,
     While,
 
+    // A word the dialect keeps from being used as an identifier
+    Reserved,
+
     // Special type meaning end of file
     Eof,
 }
 
+/// Binding strength of binary operators, from loosest to tightest.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Precedence {
+    Or,
+    And,
+    Equality,
+    Comparison,
+    Term,
+    Factor,
+    Exponent,
+}
+
+impl TokenType {
+    /// Whether this is one of the reserved words. Classification goes by
+    /// token type, so a dialect spelling `!` as `not` still scans an
+    /// operator.
+    pub fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            Self::And
+                | Self::Class
+                | Self::Else
+                | Self::False
+                | Self::For
+                | Self::Fun
+                | Self::If
+                | Self::Nil
+                | Self::Or
+                | Self::Print
+                | Self::Return
+                | Self::Super
+                | Self::This
+                | Self::True
+                | Self::Var
+                | Self::While
+                | Self::Reserved
+        )
+    }
+
+    /// Whether this is an arithmetic, comparison, logical or assignment
+    /// operator written with symbols, or the `?` of the ternary operator.
+    /// Punctuation such as parentheses, `.` and the arrows is not included,
+    /// nor is `:`, which also separates keys from values in map literals.
+    pub fn is_operator(&self) -> bool {
+        matches!(
+            self,
+            Self::Minus
+                | Self::Plus
+                | Self::Star
+                | Self::Slash
+                | Self::Percent
+                | Self::StarStar
+                | Self::Bang
+                | Self::BangEqual
+                | Self::Equal
+                | Self::EqualEqual
+                | Self::Less
+                | Self::LessEqual
+                | Self::Greater
+                | Self::GreaterEqual
+                | Self::PlusEqual
+                | Self::MinusEqual
+                | Self::StarEqual
+                | Self::SlashEqual
+                | Self::PlusPlus
+                | Self::MinusMinus
+                | Self::Question
+        )
+    }
+
+    /// Whether tokens of this type carry a [`Literal`] value.
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self,
+            Self::String | Self::Number | Self::StringStart | Self::StringMiddle | Self::StringEnd
+        )
+    }
+
+    /// The precedence of this token used as a binary operator, or `None` if
+    /// it is not one. Everything is left associative except `**`.
+    pub fn binary_precedence(&self) -> Option<Precedence> {
+        match self {
+            Self::Or => Some(Precedence::Or),
+            Self::And => Some(Precedence::And),
+            Self::EqualEqual | Self::BangEqual => Some(Precedence::Equality),
+            Self::Less | Self::LessEqual | Self::Greater | Self::GreaterEqual => {
+                Some(Precedence::Comparison)
+            }
+            Self::Plus | Self::Minus => Some(Precedence::Term),
+            Self::Star | Self::Slash | Self::Percent => Some(Precedence::Factor),
+            Self::StarStar => Some(Precedence::Exponent),
+            _ => None,
+        }
+    }
+
+    pub fn is_right_associative(&self) -> bool {
+        *self == Self::StarStar
+    }
+}
+
 impl std::fmt::Display for TokenType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -68,6 +195,11 @@ impl std::fmt::Display for TokenType {
             Self::Plus => write!(f, "PLUS"),
             Self::Semicolon => write!(f, "SEMICOLON"),
             Self::Star => write!(f, "STAR"),
+            Self::Percent => write!(f, "PERCENT"),
+            Self::LeftBracket => write!(f, "LEFT_BRACKET"),
+            Self::RightBracket => write!(f, "RIGHT_BRACKET"),
+            Self::Colon => write!(f, "COLON"),
+            Self::Question => write!(f, "QUESTION"),
             Self::Bang => write!(f, "BANG"),
             Self::BangEqual => write!(f, "BANG_EQUAL"),
             Self::Equal => write!(f, "EQUAL"),
@@ -77,8 +209,20 @@ impl std::fmt::Display for TokenType {
             Self::Greater => write!(f, "GREATER"),
             Self::GreaterEqual => write!(f, "GREATER_EQUAL"),
             Self::Slash => write!(f, "SLASH"),
+            Self::StarStar => write!(f, "STAR_STAR"),
+            Self::PlusEqual => write!(f, "PLUS_EQUAL"),
+            Self::MinusEqual => write!(f, "MINUS_EQUAL"),
+            Self::StarEqual => write!(f, "STAR_EQUAL"),
+            Self::SlashEqual => write!(f, "SLASH_EQUAL"),
+            Self::PlusPlus => write!(f, "PLUS_PLUS"),
+            Self::MinusMinus => write!(f, "MINUS_MINUS"),
+            Self::Arrow => write!(f, "ARROW"),
+            Self::FatArrow => write!(f, "FAT_ARROW"),
             Self::String => write!(f, "STRING"),
             Self::Number => write!(f, "NUMBER"),
+            Self::StringStart => write!(f, "STRING_START"),
+            Self::StringMiddle => write!(f, "STRING_MIDDLE"),
+            Self::StringEnd => write!(f, "STRING_END"),
             Self::Identifier => write!(f, "IDENTIFIER"),
             Self::And => write!(f, "AND"),
             Self::Class => write!(f, "CLASS"),
@@ -96,53 +240,221 @@ impl std::fmt::Display for TokenType {
             Self::True => write!(f, "TRUE"),
             Self::Var => write!(f, "VAR"),
             Self::While => write!(f, "WHILE"),
+            Self::Reserved => write!(f, "RESERVED"),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub enum Literal {
-    String(String),
-    Number(f64),
+#[derive(Debug, PartialEq, Clone)]
+pub enum Literal<'a> {
+    String(Cow<'a, str>),
+    /// A number literal without a fraction or exponent
+    Integer(i64),
+    Float(f64),
     // Bool(..)
     None,
 }
 
-impl std::fmt::Display for Literal {
+impl Literal<'_> {
+    pub fn into_owned(self) -> Literal<'static> {
+        match self {
+            Self::String(s) => Literal::String(Cow::Owned(s.into_owned())),
+            Self::Integer(n) => Literal::Integer(n),
+            Self::Float(n) => Literal::Float(n),
+            Self::None => Literal::None,
+        }
+    }
+}
+
+impl std::fmt::Display for Literal<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::None => write!(f, "null"),
             Self::String(s) => write!(f, "{}", s),
-            Self::Number(n) => write!(f, "{:?}", n),
+            // integers print like floats, e.g. `42.0`, and from 1e16 on in
+            // exponent notation like an f64 does
+            Self::Integer(n) if n.unsigned_abs() >= 10_000_000_000_000_000 => {
+                write!(f, "{:?}", *n as f64)
+            }
+            Self::Integer(n) => write!(f, "{}.0", n),
+            Self::Float(n) => write!(f, "{:?}", n),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TriviaKind {
+    /// Spaces, tabs and carriage returns
+    Whitespace,
+    Newline,
+    LineComment,
+    /// A possibly nested `/* ... */` comment
+    BlockComment,
+    /// A UTF-8 byte order mark at the start of the source
+    Bom,
+    /// A `#!` interpreter line at the start of the source
+    Shebang,
+    /// Source text that could not be scanned into a token and was reported
+    /// as an error instead
+    Skipped,
+}
+
+/// Source text that does not affect the meaning of the program but is kept
+/// around so the original input can be reproduced from the token stream.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia<'a> {
+    kind: TriviaKind,
+    text: Cow<'a, str>,
+    span: Span,
+}
+
+impl<'a> Trivia<'a> {
+    pub fn new(kind: TriviaKind, text: impl Into<Cow<'a, str>>, span: Span) -> Self {
+        Self {
+            kind,
+            text: text.into(),
+            span,
         }
     }
+
+    pub fn into_owned(self) -> Trivia<'static> {
+        Trivia {
+            kind: self.kind,
+            text: Cow::Owned(self.text.into_owned()),
+            span: self.span,
+        }
+    }
+
+    pub fn kind(&self) -> TriviaKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Token {
+/// A token whose lexeme and string literal borrow from the source when
+/// possible. Use [`Token::into_owned`] when it has to outlive the source.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token<'a> {
     token_type: TokenType,
-    lexeme: String,
-    literal: Literal,
-    line: NonZeroUsize,
+    lexeme: Cow<'a, str>,
+    literal: Literal<'a>,
+    span: Span,
+    leading_trivia: Vec<Trivia<'a>>,
+    trailing_trivia: Vec<Trivia<'a>>,
 }
 
-impl Token {
+impl<'a> Token<'a> {
     pub fn new(
         token_type: TokenType,
-        lexeme: String,
-        literal: Literal,
-        line: NonZeroUsize,
+        lexeme: impl Into<Cow<'a, str>>,
+        literal: Literal<'a>,
+        span: Span,
     ) -> Self {
         Self {
             token_type,
-            lexeme,
+            lexeme: lexeme.into(),
             literal,
-            line,
+            span,
+            leading_trivia: vec![],
+            trailing_trivia: vec![],
         }
     }
+
+    /// Attaches trivia to the token. Leading trivia is everything between
+    /// the previous token's trailing trivia and this token, trailing trivia
+    /// runs up to, but not including, the next newline.
+    pub fn with_trivia(mut self, leading: Vec<Trivia<'a>>, trailing: Vec<Trivia<'a>>) -> Self {
+        self.leading_trivia = leading;
+        self.trailing_trivia = trailing;
+        self
+    }
+
+    /// Moves the spans of the token and its trivia, see [`Span::relocated`].
+    pub(crate) fn relocated(mut self, from: Span, to: Span) -> Self {
+        self.span = self.span.relocated(from, to);
+        for trivia in self
+            .leading_trivia
+            .iter_mut()
+            .chain(self.trailing_trivia.iter_mut())
+        {
+            trivia.span = trivia.span.relocated(from, to);
+        }
+        self
+    }
+
+    pub fn into_owned(self) -> Token<'static> {
+        Token {
+            token_type: self.token_type,
+            lexeme: Cow::Owned(self.lexeme.into_owned()),
+            literal: self.literal.into_owned(),
+            span: self.span,
+            leading_trivia: self
+                .leading_trivia
+                .into_iter()
+                .map(Trivia::into_owned)
+                .collect(),
+            trailing_trivia: self
+                .trailing_trivia
+                .into_iter()
+                .map(Trivia::into_owned)
+                .collect(),
+        }
+    }
+
+    pub fn kind(&self) -> TokenType {
+        self.token_type
+    }
+
+    pub fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    pub fn literal(&self) -> &Literal<'a> {
+        &self.literal
+    }
+
+    /// The lexeme in Unicode Normalization Form C. Identifiers should be
+    /// compared by name, so that a precomposed `é` and an `e` followed by a
+    /// combining accent refer to the same variable.
+    pub fn name(&self) -> Cow<'_, str> {
+        if unicode::is_nfc(&self.lexeme) {
+            Cow::Borrowed(&self.lexeme)
+        } else {
+            Cow::Owned(unicode::nfc(&self.lexeme))
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The file the token was scanned from.
+    pub fn source(&self) -> SourceId {
+        self.span.source
+    }
+
+    /// The line the token starts on.
+    pub fn line(&self) -> NonZeroUsize {
+        self.span.start_pos.line
+    }
+
+    pub fn leading_trivia(&self) -> &[Trivia<'a>] {
+        &self.leading_trivia
+    }
+
+    pub fn trailing_trivia(&self) -> &[Trivia<'a>] {
+        &self.trailing_trivia
+    }
 }
 
-impl std::fmt::Display for Token {
+impl std::fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} {} {}", self.token_type, self.lexeme, self.literal,)
     }
@@ -156,12 +468,82 @@ mod tests {
     fn test_token_print() {
         let tk = Token {
             token_type: TokenType::Eof,
-            lexeme: String::from(""),
+            lexeme: Cow::Borrowed(""),
             literal: Literal::None,
-            line: NonZeroUsize::new(1).unwrap(),
+            span: Span::default(),
+            leading_trivia: vec![],
+            trailing_trivia: vec![],
         };
         let print = tk.to_string();
 
         assert_eq!(print, String::from("EOF  null"))
     }
+
+    #[test]
+    fn test_into_owned_outlives_source() {
+        let token = {
+            let source = String::from("\"foo\"");
+            let token = Token::new(
+                TokenType::String,
+                source.as_str(),
+                Literal::String(Cow::Borrowed(&source[1..4])),
+                Span::default(),
+            );
+            token.into_owned()
+        };
+
+        assert_eq!(token.to_string(), "STRING \"foo\" foo");
+    }
+
+    #[test]
+    fn test_token_accessors() {
+        let token = Token::new(
+            TokenType::String,
+            "\"hi\"",
+            Literal::String(Cow::Borrowed("hi")),
+            Span::default(),
+        );
+
+        assert_eq!(token.kind(), TokenType::String);
+        assert_eq!(token.lexeme(), "\"hi\"");
+        assert_eq!(token.literal(), &Literal::String(Cow::Borrowed("hi")));
+        assert_eq!(token.span(), Span::default());
+    }
+
+    #[test]
+    fn test_token_type_classification() {
+        assert!(TokenType::While.is_keyword());
+        assert!(TokenType::Reserved.is_keyword());
+        assert!(!TokenType::Identifier.is_keyword());
+
+        assert!(TokenType::PlusEqual.is_operator());
+        assert!(TokenType::BangEqual.is_operator());
+        assert!(!TokenType::LeftParen.is_operator());
+        assert!(!TokenType::Arrow.is_operator());
+        assert!(!TokenType::And.is_operator());
+        assert!(TokenType::Question.is_operator());
+        assert!(!TokenType::Colon.is_operator());
+
+        assert!(TokenType::Number.is_literal());
+        assert!(TokenType::StringMiddle.is_literal());
+        assert!(!TokenType::True.is_literal());
+    }
+
+    #[test]
+    fn test_binary_precedence() {
+        let precedence = |tt: TokenType| tt.binary_precedence().unwrap();
+
+        assert!(precedence(TokenType::Or) < precedence(TokenType::And));
+        assert!(precedence(TokenType::And) < precedence(TokenType::EqualEqual));
+        assert!(precedence(TokenType::EqualEqual) < precedence(TokenType::Less));
+        assert!(precedence(TokenType::Less) < precedence(TokenType::Plus));
+        assert!(precedence(TokenType::Minus) < precedence(TokenType::Percent));
+        assert!(precedence(TokenType::Star) < precedence(TokenType::StarStar));
+        assert_eq!(precedence(TokenType::Plus), precedence(TokenType::Minus));
+
+        assert_eq!(TokenType::Bang.binary_precedence(), None);
+        assert_eq!(TokenType::Equal.binary_precedence(), None);
+        assert!(TokenType::StarStar.is_right_associative());
+        assert!(!TokenType::Star.is_right_associative());
+    }
 }
@@ -0,0 +1,253 @@
+//! The Unicode properties identifiers are checked against: which characters
+//! may start and continue an identifier (UAX #31), Normalization Form C
+//! (UAX #15) to compare names, and the mixed-script detection of UTS #39.
+//!
+//! The data lives in `tables`, generated by `tools/unicode-tables`, so the
+//! scanner needs no dependencies beyond the ones CodeCrafters provides.
+
+#[rustfmt::skip]
+mod tables;
+
+use std::cmp::Ordering;
+
+fn in_ranges(c: char, ranges: &[(char, char)]) -> bool {
+    ranges
+        .binary_search_by(|&(start, end)| range_cmp(c, start, end))
+        .is_ok()
+}
+
+/// Where the range `start..=end` lies relative to `c`.
+fn range_cmp(c: char, start: char, end: char) -> Ordering {
+    if end < c {
+        Ordering::Less
+    } else if start > c {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    }
+}
+
+/// Whether `c` has the `XID_Start` property. `_` does not.
+pub(crate) fn is_xid_start(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_alphabetic()
+    } else {
+        in_ranges(c, tables::XID_START)
+    }
+}
+
+/// Whether `c` has the `XID_Continue` property.
+pub(crate) fn is_xid_continue(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_alphanumeric() || c == '_'
+    } else {
+        in_ranges(c, tables::XID_CONTINUE)
+    }
+}
+
+// Hangul syllables are composed of jamo by arithmetic instead of tables.
+const S_BASE: u32 = 0xAC00;
+const L_BASE: u32 = 0x1100;
+const V_BASE: u32 = 0x1161;
+const T_BASE: u32 = 0x11A7;
+const L_COUNT: u32 = 19;
+const V_COUNT: u32 = 21;
+const T_COUNT: u32 = 28;
+const N_COUNT: u32 = V_COUNT * T_COUNT;
+const S_COUNT: u32 = L_COUNT * N_COUNT;
+
+fn combining_class(c: char) -> u8 {
+    if c < '\u{300}' {
+        return 0;
+    }
+    tables::COMBINING_CLASS
+        .binary_search_by(|&(start, end, _)| range_cmp(c, start, end))
+        .map_or(0, |i| tables::COMBINING_CLASS[i].2)
+}
+
+fn decompose(c: char, out: &mut Vec<char>) {
+    let s = (c as u32).wrapping_sub(S_BASE);
+    if s < S_COUNT {
+        let jamo = |code| char::from_u32(code).expect("jamo are chars");
+        out.push(jamo(L_BASE + s / N_COUNT));
+        out.push(jamo(V_BASE + s % N_COUNT / T_COUNT));
+        if s % T_COUNT != 0 {
+            out.push(jamo(T_BASE + s % T_COUNT));
+        }
+        return;
+    }
+    match tables::DECOMPOSITION.binary_search_by_key(&c, |&(c, _)| c) {
+        Ok(i) => out.extend_from_slice(tables::DECOMPOSITION[i].1),
+        Err(_) => out.push(c),
+    }
+}
+
+/// The primary composite of `a` and `b`, if there is one.
+fn compose(a: char, b: char) -> Option<char> {
+    let (a_code, b_code) = (a as u32, b as u32);
+    let (l, v) = (a_code.wrapping_sub(L_BASE), b_code.wrapping_sub(V_BASE));
+    if l < L_COUNT && v < V_COUNT {
+        return char::from_u32(S_BASE + (l * V_COUNT + v) * T_COUNT);
+    }
+    let (s, t) = (a_code.wrapping_sub(S_BASE), b_code.wrapping_sub(T_BASE));
+    if s < S_COUNT && s % T_COUNT == 0 && 0 < t && t < T_COUNT {
+        return char::from_u32(a_code + t);
+    }
+    tables::COMPOSITION
+        .binary_search_by_key(&(a, b), |&(pair, _)| pair)
+        .ok()
+        .map(|i| tables::COMPOSITION[i].1)
+}
+
+/// Whether `s` is in Normalization Form C.
+pub(crate) fn is_nfc(s: &str) -> bool {
+    // Nothing below U+0300 decomposes or combines with what precedes it.
+    s.chars().all(|c| c < '\u{300}') || nfc(s) == s
+}
+
+/// `s` in Normalization Form C: canonically decomposed, reordered and
+/// composed again.
+pub(crate) fn nfc(s: &str) -> String {
+    let mut chars = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        decompose(c, &mut chars);
+    }
+
+    // Combining marks in a row are sorted by class, keeping the order of
+    // marks of the same class.
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        while i < chars.len() && combining_class(chars[i]) != 0 {
+            i += 1;
+        }
+        chars[start..i].sort_by_key(|&c| combining_class(c));
+        i += 1;
+    }
+
+    let mut composed: Vec<char> = Vec::with_capacity(chars.len());
+    let mut starter = None;
+    for c in chars {
+        let class = combining_class(c);
+        if let Some(starter) = starter {
+            // A mark is blocked from the starter by any character in
+            // between that is a starter or has the same or a higher class.
+            let blocked = composed.len() - 1 != starter
+                && composed
+                    .last()
+                    .map(|&last| combining_class(last))
+                    .is_some_and(|last| last == 0 || last >= class);
+            if let Some(composite) = compose(composed[starter], c).filter(|_| !blocked) {
+                composed[starter] = composite;
+                continue;
+            }
+        }
+        if class == 0 {
+            starter = Some(composed.len());
+        }
+        composed.push(c);
+    }
+    composed.into_iter().collect()
+}
+
+/// A set of scripts, augmented as UTS #39 describes so that Han also
+/// counts as Japanese, Korean and Han with Bopomofo.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct ScriptSet([u64; 3]);
+
+impl ScriptSet {
+    /// The set of characters used by every script, like digits and `_`.
+    const ALL: Self = Self([u64::MAX; 3]);
+
+    fn of(c: char) -> Self {
+        tables::SCRIPTS
+            .binary_search_by(|&(start, end, _)| range_cmp(c, start, end))
+            .map_or(Self([0; 3]), |i| {
+                Self(tables::SCRIPT_SETS[tables::SCRIPTS[i].2 as usize])
+            })
+    }
+
+    fn intersection(self, other: Self) -> Self {
+        let [a, b, c] = self.0;
+        let [d, e, f] = other.0;
+        Self([a & d, b & e, c & f])
+    }
+
+    fn is_empty(self) -> bool {
+        self.0 == [0; 3]
+    }
+
+    fn shares_script(a: char, b: char) -> bool {
+        !Self::of(a).intersection(Self::of(b)).is_empty()
+    }
+}
+
+/// Whether all characters of `s` can be written in a single script.
+pub(crate) fn is_single_script(s: &str) -> bool {
+    !s.chars()
+        .map(ScriptSet::of)
+        .fold(ScriptSet::ALL, ScriptSet::intersection)
+        .is_empty()
+}
+
+/// Whether `c` looks like a character of another script.
+pub(crate) fn is_mixed_script_confusable(c: char) -> bool {
+    tables::MIXED_SCRIPT_CONFUSABLES.binary_search(&c).is_ok()
+}
+
+/// Whether an identifier could be mistaken for a different one, like a
+/// Latin word with a Cyrillic `а` inside of it.
+///
+/// Each word of the name, separated by `_` or digits, is checked on its
+/// own, so `x_переменная` and `user_名前` are fine. A word is confusable if
+/// it contains a character that looks like one of another script and is
+/// not written in the script of most of the word, so `user名前` is fine as
+/// well.
+pub(crate) fn is_confusable(name: &str) -> bool {
+    name.split(|c| ScriptSet::of(c) == ScriptSet::ALL)
+        .filter(|word| !is_single_script(word))
+        .any(|word| {
+            let main = word.chars().max_by_key(|&c| {
+                word.chars()
+                    .filter(|&other| ScriptSet::shares_script(c, other))
+                    .count()
+            });
+            word.chars().any(|c| {
+                main.is_some_and(|main| !ScriptSet::shares_script(c, main))
+                    && is_mixed_script_confusable(c)
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xid() {
+        assert!(is_xid_start('a') && is_xid_start('é') && is_xid_start('名'));
+        assert!(!is_xid_start('_') && !is_xid_start('1') && !is_xid_start('€'));
+        assert!(is_xid_continue('_') && is_xid_continue('1'));
+        assert!(is_xid_continue('\u{301}') && !is_xid_continue('-'));
+    }
+
+    #[test]
+    fn test_nfc() {
+        assert_eq!(nfc("cafe\u{301}"), "café");
+        assert_eq!(nfc("café"), "café");
+        // U+0323 has a lower class than U+0302, so it is moved in front
+        // and composes first.
+        assert_eq!(nfc("e\u{302}\u{323}"), "\u{1ec7}");
+        assert_eq!(nfc("\u{1100}\u{1161}\u{11a8}"), "각");
+        assert_eq!(nfc("\u{212b}"), "\u{c5}");
+        assert!(is_nfc("café 각") && !is_nfc("cafe\u{301}") && !is_nfc("\u{212b}"));
+    }
+
+    #[test]
+    fn test_scripts() {
+        assert!(is_single_script("user_1") && is_single_script("名前なまえ"));
+        assert!(!is_single_script("user名前"));
+        assert!(is_confusable("pаypal"));
+        assert!(!is_confusable("user名前") && !is_confusable("x_переменная"));
+    }
+}
@@ -0,0 +1,306 @@
+//! Generates `src/syntax/unicode/tables.rs`, the Unicode data the scanner
+//! needs for identifiers, from the crates that implement the standards. The
+//! scanner does not depend on those crates so the CodeCrafters manifest
+//! stays untouched.
+//!
+//! Run from the repository root with
+//!
+//! ```text
+//! cargo run --manifest-path tools/unicode-tables/Cargo.toml > src/syntax/unicode/tables.rs
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+use unicode_normalization::char::{canonical_combining_class, compose, decompose_canonical};
+use unicode_script::{Script, UnicodeScript};
+use unicode_security::is_potential_mixed_script_confusable_char;
+use unicode_xid::UnicodeXID;
+
+/// Hangul syllables are composed and decomposed algorithmically.
+const HANGUL: std::ops::RangeInclusive<char> = '\u{AC00}'..='\u{D7A3}';
+
+/// A char literal, escaped so the tables are plain ASCII.
+fn lit(c: char) -> String {
+    format!("'\\u{{{:x}}}'", c as u32)
+}
+
+fn chars() -> impl Iterator<Item = char> {
+    (0..=char::MAX as u32).filter_map(char::from_u32)
+}
+
+/// Collapses consecutive characters with the same value into ranges.
+fn ranges<T: PartialEq + Copy>(values: impl Iterator<Item = (char, T)>) -> Vec<(char, char, T)> {
+    let mut ranges: Vec<(char, char, T)> = vec![];
+    for (c, value) in values {
+        match ranges.last_mut() {
+            Some((_, end, last)) if *last == value && *end as u32 + 1 == c as u32 => *end = c,
+            _ => ranges.push((c, c, value)),
+        }
+    }
+    ranges
+}
+
+fn table(out: &mut String, declaration: &str, entries: impl IntoIterator<Item = String>) {
+    writeln!(out, "\n{declaration} = &[").unwrap();
+    let mut line = String::new();
+    for entry in entries {
+        if !line.is_empty() && line.len() + entry.len() > 94 {
+            writeln!(out, "   {line}").unwrap();
+            line.clear();
+        }
+        write!(line, " {entry},").unwrap();
+    }
+    if !line.is_empty() {
+        writeln!(out, "   {line}").unwrap();
+    }
+    out.push_str("];\n");
+}
+
+fn char_ranges(out: &mut String, name: &str, predicate: impl Fn(char) -> bool) {
+    let ranges = ranges(chars().filter(|&c| predicate(c)).map(|c| (c, ())));
+    table(
+        out,
+        &format!("pub(super) const {name}: &[(char, char)]"),
+        ranges
+            .iter()
+            .map(|&(start, end, _)| format!("({}, {})", lit(start), lit(end))),
+    );
+}
+
+fn decomposition(c: char) -> Vec<char> {
+    let mut chars = vec![];
+    decompose_canonical(c, |d| chars.push(d));
+    chars
+}
+
+/// The distinct script sets, and the index of the set of each range of
+/// characters.
+type ScriptSets = (Vec<[u64; 3]>, Vec<(char, char, usize)>);
+
+/// Each script gets a bit, followed by the three augmented script sets of
+/// UTS #39: Han with Bopomofo, Japanese and Korean.
+fn script_sets() -> ScriptSets {
+    let mut bits: HashMap<Script, usize> = HashMap::new();
+    for c in chars() {
+        for script in c.script_extension().iter() {
+            let next = bits.len();
+            bits.entry(script).or_insert(next);
+        }
+    }
+    let (hanb, jpan, kore) = (bits.len(), bits.len() + 1, bits.len() + 2);
+    assert!(kore < 192, "too many scripts for the bit set");
+
+    let mut sets = vec![[u64::MAX; 3]];
+    let mut index = HashMap::from([([u64::MAX; 3], 0)]);
+    let mut set_of = |c: char| {
+        let extension = c.script_extension();
+        if extension.is_empty() {
+            return None;
+        }
+        let mut set = [u64::MAX; 3];
+        if !(extension.is_common() || extension.is_inherited()) {
+            set = [0; 3];
+            let mut add = |bit: usize| set[bit / 64] |= 1 << (bit % 64);
+            for script in extension.iter() {
+                add(bits[&script]);
+                match script {
+                    Script::Han => [hanb, jpan, kore].into_iter().for_each(&mut add),
+                    Script::Hiragana | Script::Katakana => add(jpan),
+                    Script::Hangul => add(kore),
+                    Script::Bopomofo => add(hanb),
+                    _ => {}
+                }
+            }
+        }
+        let next = sets.len();
+        Some(*index.entry(set).or_insert_with(|| {
+            sets.push(set);
+            next
+        }))
+    };
+    let ranges = ranges(chars().filter_map(|c| Some((c, set_of(c)?))));
+    (sets, ranges)
+}
+
+fn main() {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "//! Generated by tools/unicode-tables from unicode-xid 0.2.6,\n\
+         //! unicode-normalization 0.1.25, unicode-script 0.5.8 and unicode-security\n\
+         //! 0.1.2. Do not edit."
+    )
+    .unwrap();
+
+    char_ranges(&mut out, "XID_START", |c| c.is_xid_start());
+    char_ranges(&mut out, "XID_CONTINUE", |c| c.is_xid_continue());
+
+    let classes = ranges(chars().map(|c| (c, canonical_combining_class(c))));
+    table(
+        &mut out,
+        "pub(super) const COMBINING_CLASS: &[(char, char, u8)]",
+        classes
+            .iter()
+            .filter(|(_, _, class)| *class != 0)
+            .map(|&(start, end, class)| format!("({}, {}, {class})", lit(start), lit(end))),
+    );
+
+    let decompositions: BTreeMap<char, Vec<char>> = chars()
+        .filter(|c| !HANGUL.contains(c))
+        .map(|c| (c, decomposition(c)))
+        .filter(|(c, d)| d != &[*c])
+        .collect();
+    table(
+        &mut out,
+        "pub(super) const DECOMPOSITION: &[(char, &[char])]",
+        decompositions.iter().map(|(c, d)| {
+            let d: Vec<_> = d.iter().map(|&c| lit(c)).collect();
+            format!("({}, &[{}])", lit(*c), d.join(", "))
+        }),
+    );
+
+    // Compositions chain, `e` and U+0323 compose to `ẹ`, which composes
+    // with U+0302, so the first character may be composed itself.
+    let parts: BTreeSet<char> = decompositions
+        .iter()
+        .flat_map(|(c, d)| d.iter().copied().chain([*c]))
+        .collect();
+    let compositions: BTreeMap<(char, char), char> = parts
+        .iter()
+        .flat_map(|&a| parts.iter().map(move |&b| (a, b)))
+        .filter_map(|(a, b)| Some(((a, b), compose(a, b)?)))
+        .filter(|(_, c)| !HANGUL.contains(c))
+        .collect();
+    table(
+        &mut out,
+        "pub(super) const COMPOSITION: &[((char, char), char)]",
+        compositions
+            .iter()
+            .map(|(&(a, b), &c)| format!("(({}, {}), {})", lit(a), lit(b), lit(c))),
+    );
+
+    let (sets, scripts) = script_sets();
+    table(
+        &mut out,
+        "pub(super) const SCRIPT_SETS: &[[u64; 3]]",
+        sets.iter()
+            .map(|[a, b, c]| format!("[{a:#x}, {b:#x}, {c:#x}]")),
+    );
+    table(
+        &mut out,
+        "pub(super) const SCRIPTS: &[(char, char, u16)]",
+        scripts
+            .iter()
+            .map(|&(start, end, set)| format!("({}, {}, {set})", lit(start), lit(end))),
+    );
+
+    table(
+        &mut out,
+        "pub(super) const MIXED_SCRIPT_CONFUSABLES: &[char]",
+        chars()
+            .filter(|&c| is_potential_mixed_script_confusable_char(c))
+            .map(lit),
+    );
+
+    print!("{out}");
+}
+
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../../../src/syntax/unicode/mod.rs"]
+mod vendored;
+
+/// Checks the vendored module against the crates the tables come from. Run
+/// with `cargo test --release --manifest-path tools/unicode-tables/Cargo.toml`.
+#[cfg(test)]
+mod tests {
+    use unicode_normalization::UnicodeNormalization;
+    use unicode_security::mixed_script::AugmentedScriptSet;
+    use unicode_security::MixedScript;
+
+    use super::*;
+
+    /// A few thousand strings of letters, marks, jamo and syllables.
+    fn samples() -> impl Iterator<Item = String> {
+        let pool: Vec<char> = "aeEoOuAsz1_ \u{300}\u{301}\u{302}\u{303}\u{308}\u{323}\u{327}\u{328}\u{31b}\u{345}\u{5b0}\u{5b4}\u{5bc}\u{3099}\u{309a}\u{1100}\u{1161}\u{11a8}\u{ac00}\u{ac01}\u{212b}\u{1e0b}\u{3b1}\u{3c9}\u{430}\u{435}\u{43e}\u{5d0}名前なカ가ㄅ"
+            .chars()
+            .collect();
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        (0..20_000).map(move |_| {
+            let len = 1 + state as usize % 6;
+            (0..len)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    pool[state as usize % pool.len()]
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_characters() {
+        for c in chars() {
+            assert_eq!(vendored::is_xid_start(c), c.is_xid_start(), "{c:?}");
+            assert_eq!(vendored::is_xid_continue(c), c.is_xid_continue(), "{c:?}");
+            assert_eq!(
+                vendored::is_mixed_script_confusable(c),
+                is_potential_mixed_script_confusable_char(c),
+                "{c:?}"
+            );
+            let s = c.to_string();
+            assert_eq!(vendored::nfc(&s), s.nfc().collect::<String>(), "{c:?}");
+            assert_eq!(
+                vendored::is_nfc(&s),
+                unicode_normalization::is_nfc(&s),
+                "{c:?}"
+            );
+            let with_latin = format!("a{c}");
+            assert_eq!(
+                vendored::is_single_script(&with_latin),
+                with_latin.is_single_script(),
+                "{c:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_strings() {
+        let is_confusable = |name: &str| {
+            let shares_script = |a: char, b: char| {
+                let mut scripts = AugmentedScriptSet::for_char(a);
+                scripts.intersect_with(AugmentedScriptSet::for_char(b));
+                !scripts.is_empty()
+            };
+            name.split(|c| AugmentedScriptSet::for_char(c).is_all())
+                .filter(|word| !word.is_single_script())
+                .any(|word| {
+                    let main = word.chars().max_by_key(|&c| {
+                        word.chars()
+                            .filter(|&other| shares_script(c, other))
+                            .count()
+                    });
+                    word.chars().any(|c| {
+                        main.is_some_and(|main| !shares_script(c, main))
+                            && is_potential_mixed_script_confusable_char(c)
+                    })
+                })
+        };
+        for s in samples() {
+            assert_eq!(vendored::nfc(&s), s.nfc().collect::<String>(), "{s:?}");
+            assert_eq!(
+                vendored::is_nfc(&s),
+                unicode_normalization::is_nfc(&s),
+                "{s:?}"
+            );
+            assert_eq!(
+                vendored::is_single_script(&s),
+                s.is_single_script(),
+                "{s:?}"
+            );
+            assert_eq!(vendored::is_confusable(&s), is_confusable(&s), "{s:?}");
+        }
+    }
+}